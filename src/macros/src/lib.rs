@@ -1,43 +1,74 @@
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{Attribute, ItemStruct};
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, ItemStruct, Token};
 
 #[proc_macro_attribute]
 pub fn table_name(_: TokenStream, item: TokenStream) -> TokenStream {
     item
 }
 
-#[proc_macro_derive(CreateTable, attributes(primary))]
+#[proc_macro_derive(CreateTable, attributes(primary, text, column, references))]
 pub fn derive_create_table(stream: TokenStream) -> TokenStream {
-    let input: ItemStruct = syn::parse(stream).unwrap();
-    let table_name = determine_table_name(&input.attrs);
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(create_table(&input))
+}
+
+fn create_table(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
 
     let mut sql = String::from("CREATE TABLE IF NOT EXISTS ");
     sql.push_str(&table_name);
     sql.push('(');
-    for field in &input.fields {
-        let column_name = field.ident.as_ref().unwrap().to_token_stream().to_string();
-        sql.push_str(&column_name);
-
-        let ty = field.ty.to_token_stream().to_string();
-        if ty == "String" {
-            sql.push_str(" TEXT NOT NULL");
-        } else {
-            sql.push_str(" INTEGER NOT NULL");
-        }
-        for attr in &field.attrs {
-            let attr = attr.path.to_token_stream().to_string();
-            if attr == "primary" {
-                sql.push_str(" PRIMARY KEY");
-                break;
-            }
+    let columns = stored_columns(input)?;
+    for column in &columns {
+        sql.push_str(&column.name);
+        sql.push(' ');
+        sql.push_str(&column_type(column.field)?);
+        if is_primary(column.field) {
+            sql.push_str(" PRIMARY KEY");
         }
         sql.push_str(", ");
     }
+    for column in &columns {
+        if let Some((table, key)) = &column.references {
+            sql.push_str(&format!(
+                "FOREIGN KEY({}) REFERENCES {table}({key}) ON DELETE CASCADE, ",
+                column.name
+            ));
+        }
+    }
     sql.pop();
     sql.pop();
     sql.push(')');
 
+    // The expected columns compared by `sync_schema`.
+    let mut names = vec![];
+    let mut types = vec![];
+    let mut not_nulls = vec![];
+    let mut primaries = vec![];
+    let mut definitions = vec![];
+    for column in &columns {
+        let (sql_type, not_null) = column_affinity(column.field)?;
+        let primary = is_primary(column.field);
+        // `ALTER TABLE` cannot add primary keys, nor table constraints like foreign keys.
+        let definition = if primary || column.references.is_some() {
+            quote! { None }
+        } else if not_null {
+            let default = default_literal(column, sql_type)?;
+            let definition = format!("{sql_type} NOT NULL DEFAULT {default}");
+            quote! { Some(#definition) }
+        } else {
+            quote! { Some(#sql_type) }
+        };
+        names.push(column.name.clone());
+        types.push(sql_type);
+        not_nulls.push(not_null);
+        primaries.push(primary);
+        definitions.push(definition);
+    }
+
     let struct_name = &input.ident;
     let output = quote! {
         impl #struct_name {
@@ -46,64 +77,167 @@ pub fn derive_create_table(stream: TokenStream) -> TokenStream {
                 db.execute(#sql, [])?;
                 Ok(())
             }
+
+            /// Compare the table with the fields. Create the table if it does not exist, and add the
+            /// missing columns with their default values; the other differences are only reported.
+            fn sync_schema(db: &::rusqlite::Connection) -> ::rusqlite::Result<::runtime::SchemaDiff> {
+                // (name, type, not null, primary key, the definition to add it by `ALTER TABLE`)
+                let columns: &[(&str, &str, bool, bool, Option<&str>)] = &[
+                    #((#names, #types, #not_nulls, #primaries, #definitions)),*
+                ];
+                let mut diff = ::runtime::SchemaDiff::new(#table_name);
+                let existing = {
+                    let mut stmt = db.prepare("SELECT name, type, \"notnull\", pk FROM pragma_table_info(?)")?;
+                    let rows = stmt.query_map([#table_name], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?, row.get::<_, bool>(3)?))
+                    })?;
+                    rows.collect::<::rusqlite::Result<Vec<_>>>()?
+                };
+                if existing.is_empty() {
+                    Self::create_table(db)?;
+                    diff.created = true;
+                    return Ok(diff);
+                }
+                for (name, ty, not_null, primary, definition) in columns {
+                    match existing.iter().find(|c| c.0 == *name) {
+                        Some((_, actual_ty, actual_not_null, actual_primary)) => {
+                            if !actual_ty.eq_ignore_ascii_case(ty) || actual_not_null != not_null || actual_primary != primary {
+                                diff.changed.push((
+                                    name.to_string(),
+                                    ::runtime::SchemaDiff::describe(actual_ty, *actual_not_null, *actual_primary),
+                                    ::runtime::SchemaDiff::describe(ty, *not_null, *primary),
+                                ));
+                            }
+                        }
+                        None => match definition {
+                            Some(definition) => {
+                                db.execute(&format!("ALTER TABLE {} ADD COLUMN {name} {definition}", #table_name), [])?;
+                                diff.added.push(name.to_string());
+                            }
+                            None => diff.missing.push(name.to_string()),
+                        },
+                    }
+                }
+                for (name, ..) in &existing {
+                    if !columns.iter().any(|c| c.0 == name) {
+                        diff.dropped.push(name.clone());
+                    }
+                }
+                Ok(diff)
+            }
         }
     };
-    TokenStream::from(output)
+    Ok(output)
 }
 
-#[proc_macro_derive(CreateIndex, attributes(index))]
+#[proc_macro_derive(CreateIndex, attributes(index, unique, column))]
 pub fn derive_create_index(stream: TokenStream) -> TokenStream {
-    let input: ItemStruct = syn::parse(stream).unwrap();
-    let table_name = determine_table_name(&input.attrs);
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(create_index(&input))
+}
+
+fn create_index(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
 
+    let mut names = vec![];
     let mut sqls = vec![];
-    'field: for field in &input.fields {
-        for attr in &field.attrs {
-            let attr = attr.path.to_token_stream().to_string();
-            if attr == "index" {
-                let column_name = field.ident.as_ref().unwrap().to_token_stream().to_string();
-                let mut sql = String::from("CREATE INDEX IF NOT EXISTS idx_");
-                sql.push_str(&table_name);
-                sql.push('_');
-                sql.push_str(&column_name);
-                sql.push_str(" ON ");
-                sql.push_str(&table_name);
-                sql.push('(');
-                sql.push_str(&column_name);
-                sql.push(')');
-                sqls.push(sql);
-                continue 'field;
-            }
+    for index in indexes(input)? {
+        let name = format!("idx_{table_name}_{}", index.columns.join("_"));
+        if names.contains(&name) {
+            return Err(syn::Error::new_spanned(
+                index.attr,
+                format!("duplicate index on column(s) {}", index.columns.join(", ")),
+            ));
+        }
+        // Without `IF NOT EXISTS`, so that it is the same text as stored in `sqlite_master`.
+        let mut sql = String::from(if index.unique {
+            "CREATE UNIQUE INDEX "
+        } else {
+            "CREATE INDEX "
+        });
+        sql.push_str(&name);
+        sql.push_str(" ON ");
+        sql.push_str(&table_name);
+        sql.push('(');
+        sql.push_str(&index.columns.join(", "));
+        sql.push(')');
+        if let Some(predicate) = &index.predicate {
+            sql.push_str(" WHERE ");
+            sql.push_str(predicate);
         }
+        names.push(name);
+        sqls.push(sql);
     }
 
+    let prefix = format!("idx_{table_name}_");
     let struct_name = &input.ident;
     let output = quote! {
         impl #struct_name {
-            #[inline]
+            /// Create the declared indexes, dropping the ones of the table which are no longer declared
+            /// or whose definitions are changed.
             fn create_indexes(db: &::rusqlite::Connection) -> ::rusqlite::Result<()> {
-                #(
-                    db.execute(#sqls, [])?;
-                )*
+                let indexes: &[(&str, &str)] = &[#((#names, #sqls)),*];
+                let existing = {
+                    let mut stmt = db.prepare("SELECT name, sql FROM sqlite_master WHERE type='index' AND tbl_name=?")?;
+                    let rows = stmt.query_map([#table_name], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?;
+                    rows.collect::<::rusqlite::Result<Vec<_>>>()?
+                };
+                for (name, sql) in &existing {
+                    // The indexes of primary keys have no SQL.
+                    let sql = match sql {
+                        Some(sql) if name.starts_with(#prefix) => sql,
+                        _ => continue,
+                    };
+                    if !indexes.contains(&(name.as_str(), sql.as_str())) {
+                        db.execute(&format!("DROP INDEX {name}"), [])?;
+                    }
+                }
+                for (name, sql) in indexes {
+                    if !existing.iter().any(|(n, s)| n == name && s.as_deref() == Some(*sql)) {
+                        db.execute(sql, [])?;
+                    }
+                }
                 Ok(())
             }
         }
     };
-    TokenStream::from(output)
+    Ok(output)
 }
 
-#[proc_macro_derive(Select)]
+#[proc_macro_derive(Select, attributes(text, column, references))]
 pub fn derive_select(stream: TokenStream) -> TokenStream {
-    let input: ItemStruct = syn::parse(stream).unwrap();
-    let table_name = determine_table_name(&input.attrs);
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(select(&input))
+}
+
+fn select(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
 
     let mut fields = vec![];
+    let mut values = vec![];
     let mut sql = String::from("SELECT ");
-    for field in input.fields {
-        let field = field.ident.as_ref().unwrap().to_token_stream();
-        let column_name = field.to_string();
-        fields.push(field);
-        sql.push_str(&column_name);
+    for column in columns(input)? {
+        fields.push(column.ident);
+        let column_name = &column.name;
+        let field_name = column.ident.to_string();
+        let value = match (column.skip, &column.default) {
+            (true, Some(default)) => quote! { #default },
+            (true, None) => quote! { ::std::default::Default::default() },
+            (false, Some(default)) => quote! {
+                match row.get::<_, Option<_>>(#column_name).map_err(|e| column_error(e, #field_name))? {
+                    Some(value) => value,
+                    None => #default,
+                }
+            },
+            (false, None) => {
+                quote! { row.get(#column_name).map_err(|e| column_error(e, #field_name))? }
+            }
+        };
+        values.push(value);
+        if column.skip {
+            continue;
+        }
+        sql.push_str(column_name);
         sql.push_str(", ");
     }
     sql.pop();
@@ -111,7 +245,31 @@ pub fn derive_select(stream: TokenStream) -> TokenStream {
     sql.push_str(" FROM ");
     sql.push_str(&table_name);
 
+    // The typed columns used to build `runtime::dsl::Query`.
+    let mut column_fields = vec![];
+    let mut column_types = vec![];
+    let mut column_names = vec![];
+    // The loaders of the rows referencing other tables, e.g. `select_by_video` of a video's tags.
+    let mut loaders = vec![];
+    let mut loader_wheres = vec![];
+    for column in stored_columns(input)? {
+        let ty = match generic_argument(&column.field.ty, "Option") {
+            Some(ty) => ty,
+            None => &column.field.ty,
+        };
+        if column.references.is_some() {
+            loaders.push(format_ident!("select_by_{}", column.ident));
+            loader_wheres.push(format!("WHERE {}=?", column.name));
+        }
+        column_fields.push(column.ident);
+        column_types.push(ty);
+        column_names.push(column.name);
+    }
+
     let struct_name = &input.ident;
+    let struct_name_str = struct_name.to_string();
+    let vis = &input.vis;
+    let columns_name = format_ident!("{}Columns", struct_name);
     let output = quote! {
         impl #struct_name {
             fn select<P: ::rusqlite::Params>(db: &::rusqlite::Connection, where_expr: &str, where_params: P) -> ::rusqlite::Result<Vec<Self>> {
@@ -121,14 +279,22 @@ pub fn derive_select(stream: TokenStream) -> TokenStream {
                     stmt.push_str(where_expr);
                 }
                 let mut stmt = db.prepare(&stmt)?;
+                let column_error = |e: ::rusqlite::Error, field: &str| match e {
+                    ::rusqlite::Error::InvalidColumnType(i, column, ty) => {
+                        let message = format!(
+                            "cannot read column '{}.{}' into field '{}::{}'",
+                            #table_name, column, #struct_name_str, field
+                        );
+                        ::rusqlite::Error::FromSqlConversionFailure(i, ty, message.into())
+                    }
+                    e => e,
+                };
                 let mut rows = stmt.query_map(where_params, |row| {
-                    let mut entry = #struct_name::default();
-                    let mut i = 0;
-                    #(
-                        entry.#fields = row.get(i)?;
-                        i += 1;
-                    )*
-                    Ok(entry)
+                    Ok(#struct_name {
+                        #(
+                            #fields: #values,
+                        )*
+                    })
                 })?;
                 let mut result = vec![];
                 for row in rows {
@@ -136,63 +302,706 @@ pub fn derive_select(stream: TokenStream) -> TokenStream {
                 }
                 Ok(result)
             }
+
+            fn select_query(db: &::rusqlite::Connection, query: &::runtime::dsl::Query) -> ::rusqlite::Result<Vec<Self>> {
+                let (clause, params) = query.to_sql();
+                Self::select(db, &clause, params.as_slice())
+            }
+
+            #(
+                fn #loaders<V: ::rusqlite::ToSql>(db: &::rusqlite::Connection, value: V) -> ::rusqlite::Result<Vec<Self>> {
+                    Self::select(db, #loader_wheres, [value])
+                }
+            )*
+
+            fn cols() -> #columns_name {
+                #columns_name {
+                    #(
+                        #column_fields: ::runtime::dsl::Column::new(#column_names),
+                    )*
+                }
+            }
+        }
+
+        #vis struct #columns_name {
+            #(
+                pub #column_fields: ::runtime::dsl::Column<#column_types>,
+            )*
         }
     };
-    TokenStream::from(output)
+    Ok(output)
 }
 
-#[proc_macro_derive(Insert)]
+#[proc_macro_derive(Insert, attributes(text, column))]
 pub fn derive_insert(stream: TokenStream) -> TokenStream {
-    let input: ItemStruct = syn::parse(stream).unwrap();
-    let table_name = determine_table_name(&input.attrs);
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(insert(&input))
+}
+
+fn insert(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
+    let columns = stored_columns(input)?;
+    let sql = insert_sql(&table_name, &columns);
+    let fields: Vec<_> = columns.iter().map(|c| c.ident).collect();
+
+    let struct_name = &input.ident;
+    let output = quote! {
+        impl #struct_name {
+            fn insert(&self, db: &::rusqlite::Connection) -> ::rusqlite::Result<()> {
+                let mut stmt = db.prepare(#sql)?;
+                let mut params = ::rusqlite::params![#(self.#fields),*];
+                stmt.execute(params)?;
+                Ok(())
+            }
+
+            /// Insert the entries in a single transaction, so that either all or none of them are inserted.
+            fn insert_many(db: &::rusqlite::Connection, entries: &[Self]) -> ::rusqlite::Result<()> {
+                let tx = db.unchecked_transaction()?;
+                {
+                    let mut stmt = tx.prepare(#sql)?;
+                    for entry in entries {
+                        stmt.execute(::rusqlite::params![#(entry.#fields),*])?;
+                    }
+                }
+                tx.commit()
+            }
+        }
+    };
+    Ok(output)
+}
+
+#[proc_macro_derive(Upsert, attributes(primary, text, column))]
+pub fn derive_upsert(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(upsert(&input))
+}
+
+fn upsert(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
+    let primary = determine_primary_column(input)?;
+    let columns = stored_columns(input)?;
+
+    let mut sql = insert_sql(&table_name, &columns);
+    sql.push_str(" ON CONFLICT(");
+    sql.push_str(&primary.name);
+    sql.push_str(") DO ");
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|c| !is_primary(c.field) && !c.keep)
+        .map(|c| format!("{0}=excluded.{0}", c.name))
+        .collect();
+    if updates.is_empty() {
+        sql.push_str("NOTHING");
+    } else {
+        sql.push_str("UPDATE SET ");
+        sql.push_str(&updates.join(", "));
+    }
+    let fields: Vec<_> = columns.iter().map(|c| c.ident).collect();
+
+    let struct_name = &input.ident;
+    let output = quote! {
+        impl #struct_name {
+            /// Insert the entry, or update the columns of the entry with the same primary key, except
+            /// the ones marked with `#[column(keep)]`.
+            fn upsert(&self, db: &::rusqlite::Connection) -> ::rusqlite::Result<()> {
+                db.execute(#sql, ::rusqlite::params![#(self.#fields),*])?;
+                Ok(())
+            }
+
+            /// Upsert the entries in a single transaction, so that either all or none of them are written.
+            fn upsert_many(db: &::rusqlite::Connection, entries: &[Self]) -> ::rusqlite::Result<()> {
+                let tx = db.unchecked_transaction()?;
+                {
+                    let mut stmt = tx.prepare(#sql)?;
+                    for entry in entries {
+                        stmt.execute(::rusqlite::params![#(entry.#fields),*])?;
+                    }
+                }
+                tx.commit()
+            }
+        }
+    };
+    Ok(output)
+}
+
+fn insert_sql(table_name: &str, columns: &[Column]) -> String {
+    let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+    let placeholders = vec!["?"; names.len()];
+    format!(
+        "INSERT INTO {table_name}({}) VALUES ({})",
+        names.join(", "),
+        placeholders.join(",")
+    )
+}
+
+#[proc_macro_derive(Update, attributes(primary, text, column))]
+pub fn derive_update(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(update(&input))
+}
+
+fn update(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
+    let primary = determine_primary_column(input)?;
+    let primary_field = primary.ident;
 
     let mut fields = vec![];
-    let mut sql = String::from("INSERT INTO ");
+    let mut variants = vec![];
+    let mut column_names = vec![];
+    let mut sql = String::from("UPDATE ");
     sql.push_str(&table_name);
-    sql.push('(');
-    for field in input.fields {
-        let field = field.ident.as_ref().unwrap().to_token_stream();
-        let column_name = field.to_string();
-        fields.push(field);
-        sql.push_str(&column_name);
-        sql.push_str(", ");
+    sql.push_str(" SET ");
+    for column in stored_columns(input)? {
+        if is_primary(column.field) {
+            continue;
+        }
+        sql.push_str(&column.name);
+        sql.push_str("=?, ");
+        fields.push(column.ident);
+        variants.push(format_ident!("{}", camel_case(&column.ident.to_string())));
+        column_names.push(column.name);
     }
     sql.pop();
     sql.pop();
-    sql.push_str(") VALUES (");
-    for _ in 0..fields.len() {
-        sql.push_str("?,");
+    let where_sql = format!(" WHERE {}=?", primary.name);
+    sql.push_str(&where_sql);
+    let prefix = format!("UPDATE {table_name} SET ");
+
+    let struct_name = &input.ident;
+    let vis = &input.vis;
+    let field_enum = format_ident!("{}Field", struct_name);
+    let output = quote! {
+        impl #struct_name {
+            fn update(&self, db: &::rusqlite::Connection) -> ::rusqlite::Result<usize> {
+                db.execute(#sql, ::rusqlite::params![#(self.#fields,)* self.#primary_field])
+            }
+
+            fn update_fields(&self, db: &::rusqlite::Connection, fields: &[#field_enum]) -> ::rusqlite::Result<usize> {
+                let mut stmt = String::from(#prefix);
+                let mut params: Vec<&dyn ::rusqlite::ToSql> = vec![];
+                for field in fields {
+                    let column_name = match field {
+                        #(
+                            #field_enum::#variants => {
+                                params.push(&self.#fields);
+                                #column_names
+                            }
+                        )*
+                    };
+                    if params.len() > 1 {
+                        stmt.push_str(", ");
+                    }
+                    stmt.push_str(column_name);
+                    stmt.push_str("=?");
+                }
+                if params.is_empty() {
+                    return Ok(0);
+                }
+                stmt.push_str(#where_sql);
+                params.push(&self.#primary_field);
+                db.execute(&stmt, params.as_slice())
+            }
+        }
+
+        /// The fields which can be updated by `update_fields`.
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        #vis enum #field_enum {
+            #(#variants,)*
+        }
+    };
+    Ok(output)
+}
+
+/// Convert a field name like `last_played` into a variant name like `LastPlayed`.
+fn camel_case(name: &str) -> String {
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    let mut s = String::new();
+    for word in name.split('_') {
+        let mut chars = word.chars();
+        if let Some(c) = chars.next() {
+            s.push(c.to_ascii_uppercase());
+            s.extend(chars);
+        }
     }
-    sql.pop();
-    sql.push(')');
+    s
+}
+
+#[proc_macro_derive(Delete, attributes(primary, text, column))]
+pub fn derive_delete(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as ItemStruct);
+    expand(delete(&input))
+}
+
+fn delete(input: &ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let table_name = determine_table_name(input)?;
+    let sql = format!("DELETE FROM {table_name}");
 
     let struct_name = &input.ident;
+    let delete_by_primary = if input.fields.iter().any(is_primary) {
+        let primary = determine_primary_column(input)?;
+        let primary_field = primary.ident;
+        let sql = format!("DELETE FROM {table_name} WHERE {}=?", primary.name);
+        quote! {
+            fn delete(&self, db: &::rusqlite::Connection) -> ::rusqlite::Result<usize> {
+                db.execute(#sql, ::rusqlite::params![self.#primary_field])
+            }
+        }
+    } else {
+        quote! {}
+    };
     let output = quote! {
         impl #struct_name {
-            fn insert(&self, db: &::rusqlite::Connection) -> ::rusqlite::Result<()> {
-                let mut stmt = db.prepare(#sql)?;
-                let mut params = ::rusqlite::params![#(self.#fields),*];
-                stmt.execute(params)?;
-                Ok(())
+            #delete_by_primary
+
+            fn delete_where<P: ::rusqlite::Params>(db: &::rusqlite::Connection, where_expr: &str, where_params: P) -> ::rusqlite::Result<usize> {
+                let mut stmt = String::from(#sql);
+                if where_expr.len() > 0 {
+                    stmt.push(' ');
+                    stmt.push_str(where_expr);
+                }
+                db.execute(&stmt, where_params)
+            }
+        }
+    };
+    Ok(output)
+}
+
+#[proc_macro_derive(TextEnum)]
+pub fn derive_text_enum(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as syn::ItemEnum);
+    expand(text_enum(&input))
+}
+
+fn text_enum(input: &syn::ItemEnum) -> syn::Result<proc_macro2::TokenStream> {
+    let enum_name = &input.ident;
+
+    let mut variants = vec![];
+    let mut texts = vec![];
+    for variant in &input.variants {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "only unit variants can be stored as text",
+            ));
+        }
+        texts.push(variant.ident.to_string());
+        variants.push(&variant.ident);
+    }
+
+    let output = quote! {
+        impl ::rusqlite::types::ToSql for #enum_name {
+            fn to_sql(&self) -> ::rusqlite::Result<::rusqlite::types::ToSqlOutput<'_>> {
+                let text = match self {
+                    #(
+                        #enum_name::#variants => #texts,
+                    )*
+                };
+                Ok(::rusqlite::types::ToSqlOutput::from(text))
+            }
+        }
+
+        impl ::rusqlite::types::FromSql for #enum_name {
+            fn column_result(value: ::rusqlite::types::ValueRef<'_>) -> ::rusqlite::types::FromSqlResult<Self> {
+                match value.as_str()? {
+                    #(
+                        #texts => Ok(#enum_name::#variants),
+                    )*
+                    _ => Err(::rusqlite::types::FromSqlError::InvalidType),
+                }
             }
         }
     };
-    TokenStream::from(output)
+    Ok(output)
+}
+
+fn is_primary(field: &syn::Field) -> bool {
+    has_attribute(field, "primary")
+}
+
+fn has_attribute(field: &syn::Field, name: &str) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path.to_token_stream().to_string() == name)
+}
+
+struct Column<'a> {
+    field: &'a syn::Field,
+    ident: &'a syn::Ident,
+    name: String,
+    skip: bool,
+    /// Whether `upsert` keeps the stored value instead of updating it, e.g. for user data.
+    keep: bool,
+    default: Option<syn::Expr>,
+    /// The table and the column given by `#[references(table.column)]`.
+    references: Option<(String, String)>,
 }
 
-fn determine_table_name(attrs: &[Attribute]) -> String {
-    for attr in attrs {
-        if attr.path.to_token_stream().to_string() == "table_name" {
-            let name = attr.tokens.to_string();
-            if name.is_empty() {
-                panic!("attribute 'table_name' has no value");
+enum ColumnOption {
+    Name(String),
+    Skip,
+    Keep,
+    Default(Box<syn::Expr>),
+}
+
+impl Parse for ColumnOption {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: syn::Ident = input.parse()?;
+        match key.to_string().as_str() {
+            "name" => {
+                input.parse::<Token![=]>()?;
+                let name: syn::LitStr = input.parse()?;
+                Ok(ColumnOption::Name(name.value()))
             }
-            let name = name[1..name.len() - 1].to_string();
-            if name.is_empty() {
-                panic!("attribute 'table_name' has no value");
+            "skip" => Ok(ColumnOption::Skip),
+            "keep" => Ok(ColumnOption::Keep),
+            "default" => {
+                input.parse::<Token![=]>()?;
+                Ok(ColumnOption::Default(Box::new(input.parse()?)))
             }
-            return name;
+            _ => Err(syn::Error::new(
+                key.span(),
+                format!("unknown option '{key}' of attribute 'column'"),
+            )),
         }
     }
-    panic!("no attribute 'table_name'");
+}
+
+/// Collect the fields with the options given by `#[column(...)]`.
+fn columns(input: &ItemStruct) -> syn::Result<Vec<Column<'_>>> {
+    let fields =
+        match &input.fields {
+            syn::Fields::Named(fields) => &fields.named,
+            syn::Fields::Unnamed(_) => return Err(syn::Error::new_spanned(
+                &input.fields,
+                "tuple structs are not supported; the fields should be named after their columns",
+            )),
+            syn::Fields::Unit => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "unit structs are not supported; at least one named field is required",
+                ))
+            }
+        };
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.fields,
+            "at least one named field is required",
+        ));
+    }
+
+    let mut columns = vec![];
+    let mut primary_found = false;
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut column = Column {
+            field,
+            ident,
+            name: ident.to_string(),
+            skip: false,
+            keep: false,
+            default: None,
+            references: None,
+        };
+        for attr in &field.attrs {
+            if attr.path.is_ident("primary") {
+                if primary_found {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "duplicate attribute 'primary'; only one field can be the primary key",
+                    ));
+                }
+                primary_found = true;
+            }
+            if attr.path.is_ident("references") {
+                let (table, key) = attr
+                    .parse_args_with(|input: ParseStream| {
+                        let table: syn::Ident = input.parse()?;
+                        input.parse::<Token![.]>()?;
+                        let key: syn::Ident = input.parse()?;
+                        Ok((table.to_string(), key.to_string()))
+                    })
+                    .map_err(|e| {
+                        syn::Error::new(
+                            e.span(),
+                            "expected the referenced column, e.g. #[references(video.name)]",
+                        )
+                    })?;
+                column.references = Some((table, key));
+                continue;
+            }
+            if !attr.path.is_ident("column") {
+                continue;
+            }
+            let options =
+                attr.parse_args_with(Punctuated::<ColumnOption, Token![,]>::parse_terminated)?;
+            for option in options {
+                match option {
+                    ColumnOption::Name(name) => column.name = name,
+                    ColumnOption::Skip => column.skip = true,
+                    ColumnOption::Keep => column.keep = true,
+                    ColumnOption::Default(default) => column.default = Some(*default),
+                }
+            }
+        }
+        if column.skip && is_primary(field) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "primary field cannot be skipped",
+            ));
+        }
+        if column.keep && (column.skip || is_primary(field)) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "only the updated fields can be kept; skipped and primary fields are never updated",
+            ));
+        }
+        if column.skip && column.references.is_some() {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "skipped field cannot reference other tables",
+            ));
+        }
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+struct Index<'a> {
+    attr: &'a syn::Attribute,
+    unique: bool,
+    columns: Vec<String>,
+    predicate: Option<String>,
+}
+
+enum IndexOption {
+    Column(syn::Ident),
+    Where(String),
+}
+
+impl Parse for IndexOption {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![where]) {
+            input.parse::<Token![where]>()?;
+            input.parse::<Token![=]>()?;
+            let predicate: syn::LitStr = input.parse()?;
+            return Ok(IndexOption::Where(predicate.value()));
+        }
+        Ok(IndexOption::Column(input.parse()?))
+    }
+}
+
+/// Collect the indexes given by `#[index]` and `#[unique]` of fields, and the composite or partial
+/// ones given by `#[index(field, ..., where = "...")]` and `#[unique(...)]` of the struct.
+fn indexes(input: &ItemStruct) -> syn::Result<Vec<Index<'_>>> {
+    let columns = columns(input)?;
+    let mut indexes = vec![];
+    for column in &columns {
+        for attr in &column.field.attrs {
+            let unique = attr.path.is_ident("unique");
+            if !unique && !attr.path.is_ident("index") {
+                continue;
+            }
+            if column.skip {
+                return Err(syn::Error::new_spanned(
+                    column.ident,
+                    "skipped field cannot be indexed",
+                ));
+            }
+            indexes.push(Index {
+                attr,
+                unique,
+                columns: vec![column.name.clone()],
+                predicate: None,
+            });
+        }
+    }
+    for attr in &input.attrs {
+        let unique = attr.path.is_ident("unique");
+        if !unique && !attr.path.is_ident("index") {
+            continue;
+        }
+        let options =
+            attr.parse_args_with(Punctuated::<IndexOption, Token![,]>::parse_terminated)?;
+        let mut index = Index {
+            attr,
+            unique,
+            columns: vec![],
+            predicate: None,
+        };
+        for option in options {
+            match option {
+                IndexOption::Column(ident) => {
+                    let column = match columns.iter().find(|c| *c.ident == ident) {
+                        Some(column) => column,
+                        None => {
+                            return Err(syn::Error::new_spanned(
+                                &ident,
+                                format!("no field named '{ident}'"),
+                            ))
+                        }
+                    };
+                    if column.skip {
+                        return Err(syn::Error::new_spanned(
+                            &ident,
+                            "skipped field cannot be indexed",
+                        ));
+                    }
+                    index.columns.push(column.name.clone());
+                }
+                IndexOption::Where(predicate) => index.predicate = Some(predicate),
+            }
+        }
+        if index.columns.is_empty() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "at least one field should be given to the index",
+            ));
+        }
+        indexes.push(index);
+    }
+    Ok(indexes)
+}
+
+fn stored_columns(input: &ItemStruct) -> syn::Result<Vec<Column<'_>>> {
+    Ok(columns(input)?.into_iter().filter(|c| !c.skip).collect())
+}
+
+fn determine_primary_column(input: &ItemStruct) -> syn::Result<Column<'_>> {
+    match columns(input)?.into_iter().find(|c| is_primary(c.field)) {
+        Some(column) => Ok(column),
+        None => Err(syn::Error::new_spanned(
+            &input.ident,
+            "no field with attribute 'primary'",
+        )),
+    }
+}
+
+/// Map the type of the field to its column type; `Option<T>` makes the column nullable.
+fn column_type(field: &syn::Field) -> syn::Result<String> {
+    let (sql_type, not_null) = column_affinity(field)?;
+    if not_null {
+        Ok(format!("{sql_type} NOT NULL"))
+    } else {
+        Ok(sql_type.to_string())
+    }
+}
+
+/// Return the type of the column, and whether it is `NOT NULL`.
+fn column_affinity(field: &syn::Field) -> syn::Result<(&'static str, bool)> {
+    let (ty, nullable) = match generic_argument(&field.ty, "Option") {
+        Some(ty) => (ty, true),
+        None => (&field.ty, false),
+    };
+    let sql_type = if has_attribute(field, "text") {
+        "TEXT"
+    } else {
+        sql_type(ty)?
+    };
+    Ok((sql_type, !nullable))
+}
+
+/// The SQL literal of `#[column(default = ...)]` if it is a literal, otherwise the zero value of the type.
+/// Columns stored as text other than `String`, like text enums and timestamps, have no zero value
+/// that reads back, so they need a literal default.
+fn default_literal(column: &Column, sql_type: &str) -> syn::Result<String> {
+    if let Some(syn::Expr::Lit(expr)) = &column.default {
+        match &expr.lit {
+            syn::Lit::Str(s) => return Ok(format!("'{}'", s.value().replace('\'', "''"))),
+            syn::Lit::Int(i) => return Ok(i.base10_digits().to_string()),
+            syn::Lit::Float(f) => return Ok(f.base10_digits().to_string()),
+            syn::Lit::Bool(b) => return Ok(String::from(if b.value { "1" } else { "0" })),
+            _ => {}
+        }
+    }
+    let is_string = match &column.field.ty {
+        syn::Type::Path(path) => path.path.segments.last().unwrap().ident == "String",
+        _ => false,
+    };
+    let default = match sql_type {
+        "INTEGER" => "0",
+        "REAL" => "0.0",
+        "BLOB" => "X''",
+        _ if is_string => "''",
+        _ => {
+            return Err(syn::Error::new_spanned(
+                column.field,
+                "existing rows have no value for this column; \
+                make it an Option, or give it a literal #[column(default = ...)]",
+            ))
+        }
+    };
+    Ok(String::from(default))
+}
+
+fn sql_type(ty: &syn::Type) -> syn::Result<&'static str> {
+    if let Some(inner) = generic_argument(ty, "Vec") {
+        if inner.to_token_stream().to_string() == "u8" {
+            return Ok("BLOB");
+        }
+    }
+    let name = match ty {
+        syn::Type::Path(path) => path.path.segments.last().unwrap().ident.to_string(),
+        _ => String::new(),
+    };
+    let sql_type = match name.as_str() {
+        "String" => "TEXT",
+        "bool" | "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64"
+        | "usize" => "INTEGER",
+        "f32" | "f64" => "REAL",
+        // Stored as text like "2022-03-04 05:06:07" by the `chrono` feature of rusqlite.
+        "DateTime" | "NaiveDateTime" | "NaiveDate" | "NaiveTime" => "TEXT",
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "unsupported column type; mark the field with #[text] if it is stored as text",
+            ))
+        }
+    };
+    Ok(sql_type)
+}
+
+/// Return `T` if `ty` is `wrapper<T>`.
+fn generic_argument<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first()? {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn determine_table_name(input: &ItemStruct) -> syn::Result<String> {
+    for attr in &input.attrs {
+        if attr.path.is_ident("table_name") {
+            return match attr.parse_args::<syn::Ident>() {
+                Ok(name) => Ok(name.to_string()),
+                Err(e) => Err(syn::Error::new(
+                    e.span(),
+                    "expected the name of the table, e.g. #[table_name(video)]",
+                )),
+            };
+        }
+    }
+    Err(syn::Error::new_spanned(
+        &input.ident,
+        "missing attribute #[table_name(...)]",
+    ))
+}
+
+fn expand(result: syn::Result<proc_macro2::TokenStream>) -> TokenStream {
+    match result {
+        Ok(output) => TokenStream::from(output),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
 }
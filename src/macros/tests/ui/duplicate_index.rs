@@ -0,0 +1,12 @@
+use macros::*;
+
+#[derive(CreateIndex)]
+#[table_name(video_tag)]
+#[unique(video, tag)]
+#[index(video, tag, where = "tag<>''")]
+struct VideoTagEntry {
+    video: String,
+    tag: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use macros::*;
+
+#[derive(CreateTable)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    name: String,
+    #[primary]
+    file_name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use macros::*;
+
+#[derive(Select)]
+#[table_name()]
+struct VideoEntry {
+    name: String,
+}
+
+fn main() {}
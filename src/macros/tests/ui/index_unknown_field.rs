@@ -0,0 +1,11 @@
+use macros::*;
+
+#[derive(CreateIndex)]
+#[table_name(video_tag)]
+#[index(video, tags)]
+struct VideoTagEntry {
+    video: String,
+    tag: String,
+}
+
+fn main() {}
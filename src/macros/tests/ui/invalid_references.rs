@@ -0,0 +1,11 @@
+use macros::*;
+
+#[derive(CreateTable)]
+#[table_name(video_tag)]
+struct VideoTagEntry {
+    #[references(video)]
+    video: String,
+    tag: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use macros::*;
+
+#[derive(Upsert)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    #[column(keep)]
+    name: String,
+    rating: u32,
+}
+
+fn main() {}
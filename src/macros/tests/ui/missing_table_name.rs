@@ -0,0 +1,8 @@
+use macros::*;
+
+#[derive(CreateTable)]
+struct VideoEntry {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use macros::*;
+
+#[derive(TextEnum)]
+enum Source {
+    Local,
+    Remote(String),
+}
+
+fn main() {}
@@ -0,0 +1,99 @@
+use macros::*;
+
+#[derive(TextEnum, Debug, PartialEq)]
+enum Quality {
+    Low,
+    High,
+}
+
+#[derive(CreateTable, Insert, Select, Update)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    name: String,
+    rating: Option<u32>,
+    favorite: bool,
+    frame_rate: f64,
+    ratio: f32,
+    thumbnail: Vec<u8>,
+    #[text]
+    quality: Option<Quality>,
+    #[text]
+    fallback: Option<Quality>,
+}
+
+fn column_types(db: &rusqlite::Connection) -> Vec<(String, String, bool)> {
+    let mut stmt = db
+        .prepare("SELECT name, type, \"notnull\" FROM pragma_table_info('video')")
+        .unwrap();
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap();
+    rows.collect::<rusqlite::Result<_>>().unwrap()
+}
+
+fn main() {
+    let db = rusqlite::Connection::open_in_memory().unwrap();
+    VideoEntry::create_table(&db).unwrap();
+    let expected = [
+        ("name", "TEXT", true),
+        ("rating", "INTEGER", false),
+        ("favorite", "INTEGER", true),
+        ("frame_rate", "REAL", true),
+        ("ratio", "REAL", true),
+        ("thumbnail", "BLOB", true),
+        ("quality", "TEXT", false),
+        ("fallback", "TEXT", false),
+    ];
+    let expected: Vec<(String, String, bool)> = expected
+        .iter()
+        .map(|(n, t, not_null)| (n.to_string(), t.to_string(), *not_null))
+        .collect();
+    assert_eq!(column_types(&db), expected);
+
+    let mut entry = VideoEntry {
+        name: String::from("A"),
+        rating: None,
+        favorite: true,
+        frame_rate: 29.97,
+        ratio: 1.5,
+        thumbnail: vec![0, 1, 255],
+        quality: Some(Quality::High),
+        fallback: None,
+    };
+    entry.insert(&db).unwrap();
+    let stored: (Option<u32>, Option<String>, Vec<u8>, Option<String>) = db
+        .query_row(
+            "SELECT rating, quality, thumbnail, fallback FROM video",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(stored, (None, Some(String::from("High")), vec![0, 1, 255], None));
+
+    let selected = VideoEntry::select(&db, "", []).unwrap().pop().unwrap();
+    assert_eq!(selected.rating, None);
+    assert!(selected.favorite);
+    assert_eq!(selected.frame_rate, 29.97);
+    assert_eq!(selected.ratio, 1.5);
+    assert_eq!(selected.thumbnail, [0, 1, 255]);
+    assert_eq!(selected.quality, Some(Quality::High));
+    assert_eq!(selected.fallback, None);
+
+    entry.rating = Some(4);
+    entry.favorite = false;
+    entry.fallback = Some(Quality::Low);
+    entry.update(&db).unwrap();
+    let selected = VideoEntry::select(&db, "", []).unwrap().pop().unwrap();
+    assert_eq!(selected.rating, Some(4));
+    assert!(!selected.favorite);
+    assert_eq!(selected.fallback, Some(Quality::Low));
+
+    db.execute("UPDATE video SET quality='Medium'", []).unwrap();
+    let e = VideoEntry::select(&db, "", []).err().unwrap();
+    assert!(
+        e.to_string()
+            .contains("cannot read column 'video.quality' into field 'VideoEntry::quality'"),
+        "{e}"
+    );
+}
@@ -0,0 +1,49 @@
+use macros::*;
+
+#[derive(CreateIndex)]
+#[table_name(video)]
+#[index(rating, where = "favorite")]
+#[unique(file_name, rating)]
+#[allow(dead_code)]
+struct VideoEntry {
+    name: String,
+    file_name: String,
+    rating: u32,
+    favorite: bool,
+}
+
+fn index_sqls(db: &rusqlite::Connection) -> Vec<String> {
+    let mut stmt = db
+        .prepare("SELECT sql FROM sqlite_master WHERE type='index' AND sql IS NOT NULL ORDER BY name")
+        .unwrap();
+    let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+    rows.collect::<rusqlite::Result<_>>().unwrap()
+}
+
+fn main() {
+    let db = rusqlite::Connection::open_in_memory().unwrap();
+    db.execute_batch(
+        "CREATE TABLE video(name TEXT NOT NULL PRIMARY KEY, file_name TEXT NOT NULL, \
+        rating INTEGER NOT NULL, favorite INTEGER NOT NULL); \
+        CREATE INDEX idx_video_rating ON video(rating); \
+        CREATE INDEX idx_video_name ON video(name) WHERE favorite;",
+    )
+    .unwrap();
+    VideoEntry::create_indexes(&db).unwrap();
+    let expected = [
+        "CREATE UNIQUE INDEX idx_video_file_name_rating ON video(file_name, rating)",
+        "CREATE INDEX idx_video_rating ON video(rating) WHERE favorite",
+    ];
+    assert_eq!(index_sqls(&db), expected);
+    VideoEntry::create_indexes(&db).unwrap();
+    assert_eq!(index_sqls(&db), expected);
+
+    let plan: String = db
+        .query_row(
+            "EXPLAIN QUERY PLAN SELECT name FROM video WHERE favorite ORDER BY rating",
+            [],
+            |row| row.get(3),
+        )
+        .unwrap();
+    assert!(plan.contains("idx_video_rating"), "{plan}");
+}
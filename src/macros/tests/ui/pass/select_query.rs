@@ -0,0 +1,71 @@
+use macros::*;
+
+#[derive(CreateTable, Insert, Select)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    name: String,
+    rating: u32,
+    favorite: bool,
+    #[column(name = "played")]
+    play_count: u32,
+}
+
+#[derive(CreateTable, Insert, Select)]
+#[table_name(video_tag)]
+struct VideoTagEntry {
+    #[references(video.name)]
+    video: String,
+    tag: String,
+}
+
+fn main() {
+    let db = rusqlite::Connection::open_in_memory().unwrap();
+    VideoEntry::create_table(&db).unwrap();
+    VideoTagEntry::create_table(&db).unwrap();
+    for (name, rating, favorite) in [("A", 3, true), ("B", 5, false), ("C", 4, true), ("D", 1, true)] {
+        let entry = VideoEntry {
+            name: name.to_string(),
+            rating,
+            favorite,
+            play_count: rating * 2,
+        };
+        entry.insert(&db).unwrap();
+        let entry = VideoTagEntry {
+            video: name.to_string(),
+            tag: String::from("T"),
+        };
+        entry.insert(&db).unwrap();
+    }
+    let entry = VideoTagEntry {
+        video: String::from("A"),
+        tag: String::from("U"),
+    };
+    entry.insert(&db).unwrap();
+
+    let cols = VideoEntry::cols();
+    let query = runtime::dsl::Query::new()
+        .filter(cols.favorite.is_true())
+        .filter(cols.play_count.ge(4))
+        .order_by(cols.rating.desc())
+        .limit(1)
+        .offset(1);
+    let names: Vec<String> = VideoEntry::select_query(&db, &query)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(names, ["A"]);
+
+    let query = runtime::dsl::Query::new().filter(!cols.name.glob("[AB]*"));
+    let entries = VideoEntry::select_query(&db, &query).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].play_count, 8);
+
+    let tags: Vec<String> = VideoTagEntry::select_by_video(&db, "A")
+        .unwrap()
+        .into_iter()
+        .map(|e| e.tag)
+        .collect();
+    assert_eq!(tags, ["T", "U"]);
+}
@@ -0,0 +1,83 @@
+use macros::*;
+
+#[derive(TextEnum, Debug, PartialEq)]
+enum Quality {
+    Low,
+    High,
+}
+
+#[derive(CreateTable, Insert)]
+#[table_name(video)]
+struct OldVideoEntry {
+    #[primary]
+    name: String,
+    rating: u32,
+    codec: u32,
+    removed: String,
+}
+
+#[derive(CreateTable, Select)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    name: String,
+    rating: u32,
+    codec: String,
+    note: String,
+    #[column(default = 3)]
+    level: u32,
+    ratio: f64,
+    played_at: Option<i64>,
+    #[text]
+    quality: Option<Quality>,
+    added_at: Option<chrono::NaiveDateTime>,
+}
+
+fn main() {
+    let db = rusqlite::Connection::open_in_memory().unwrap();
+    let diff = OldVideoEntry::sync_schema(&db).unwrap();
+    assert!(diff.created);
+    let entry = OldVideoEntry {
+        name: String::from("A"),
+        rating: 4,
+        codec: 1,
+        removed: String::new(),
+    };
+    entry.insert(&db).unwrap();
+
+    let diff = VideoEntry::sync_schema(&db).unwrap();
+    assert!(!diff.created);
+    assert_eq!(diff.added, ["note", "level", "ratio", "played_at", "quality", "added_at"]);
+    assert!(diff.missing.is_empty());
+    assert_eq!(
+        diff.changed,
+        [(
+            String::from("codec"),
+            String::from("INTEGER NOT NULL"),
+            String::from("TEXT NOT NULL")
+        )]
+    );
+    assert_eq!(diff.dropped, ["removed"]);
+    assert_eq!(diff.problems().len(), 2);
+
+    // The changed column is left to a migration.
+    let e = VideoEntry::select(&db, "", []).err().unwrap();
+    assert!(e
+        .to_string()
+        .contains("cannot read column 'video.codec' into field 'VideoEntry::codec'"));
+    db.execute("UPDATE video SET codec='h264'", []).unwrap();
+
+    let entries = VideoEntry::select(&db, "", []).unwrap();
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.rating, 4);
+    assert_eq!(entry.note, "");
+    assert_eq!(entry.level, 3);
+    assert_eq!(entry.ratio, 0.0);
+    assert_eq!(entry.played_at, None);
+    assert_eq!(entry.quality, None);
+    assert_eq!(entry.added_at, None);
+
+    let diff = VideoEntry::sync_schema(&db).unwrap();
+    assert!(diff.added.is_empty());
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use macros::*;
+
+#[derive(Insert, Upsert, Update, Delete)]
+#[table_name(event)]
+struct EventEntry {
+    #[primary]
+    name: String,
+    at: DateTime<Utc>,
+    local: NaiveDateTime,
+    day: NaiveDate,
+    time: Option<NaiveTime>,
+}
+
+fn main() {
+    let db = rusqlite::Connection::open_in_memory().unwrap();
+    db.execute_batch(
+        "CREATE TABLE event(name TEXT NOT NULL PRIMARY KEY, at TEXT NOT NULL, \
+        local TEXT NOT NULL, day TEXT NOT NULL, time TEXT)",
+    )
+    .unwrap();
+    let day = NaiveDate::from_ymd_opt(2022, 3, 4).unwrap();
+    let mut entry = EventEntry {
+        name: String::from("a"),
+        at: Utc.with_ymd_and_hms(2022, 3, 4, 5, 6, 7).unwrap(),
+        local: day.and_hms_opt(5, 6, 7).unwrap(),
+        day,
+        time: None,
+    };
+    entry.insert(&db).unwrap();
+    entry.time = NaiveTime::from_hms_opt(8, 9, 10);
+    entry.update_fields(&db, &[EventEntryField::Time]).unwrap();
+    entry.upsert(&db).unwrap();
+    let row: (String, String, String, String) = db
+        .query_row("SELECT at, local, day, time FROM event", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .unwrap();
+    assert_eq!(row.0, "2022-03-04 05:06:07+00:00");
+    assert_eq!(row.1, "2022-03-04 05:06:07");
+    assert_eq!(row.2, "2022-03-04");
+    assert_eq!(row.3, "08:09:10");
+    let at: DateTime<Utc> = db
+        .query_row("SELECT at FROM event", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(at, entry.at);
+    let local: NaiveDateTime = db
+        .query_row("SELECT local FROM event", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(local, entry.local);
+    assert_eq!(entry.delete(&db).unwrap(), 1);
+}
@@ -0,0 +1,47 @@
+use macros::*;
+
+#[derive(CreateTable, Upsert, Select)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    name: String,
+    file_name: String,
+    #[column(keep)]
+    rating: u32,
+}
+
+fn main() {
+    let db = rusqlite::Connection::open_in_memory().unwrap();
+    VideoEntry::create_table(&db).unwrap();
+    let entry = VideoEntry {
+        name: String::from("A"),
+        file_name: String::from("a.mp4"),
+        rating: 4,
+    };
+    entry.upsert(&db).unwrap();
+    let entries = [
+        VideoEntry {
+            name: String::from("A"),
+            file_name: String::from("a.mkv"),
+            rating: 0,
+        },
+        VideoEntry {
+            name: String::from("B"),
+            file_name: String::from("b.mp4"),
+            rating: 2,
+        },
+    ];
+    VideoEntry::upsert_many(&db, &entries).unwrap();
+    let selected: Vec<(String, String, u32)> = VideoEntry::select(&db, "ORDER BY name", [])
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.name, e.file_name, e.rating))
+        .collect();
+    assert_eq!(
+        selected,
+        [
+            (String::from("A"), String::from("a.mkv"), 4),
+            (String::from("B"), String::from("b.mp4"), 2)
+        ]
+    );
+}
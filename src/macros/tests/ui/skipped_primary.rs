@@ -0,0 +1,11 @@
+use macros::*;
+
+#[derive(Delete)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    #[column(skip)]
+    name: String,
+}
+
+fn main() {}
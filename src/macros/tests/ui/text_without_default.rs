@@ -0,0 +1,18 @@
+use macros::*;
+
+#[derive(TextEnum)]
+enum Quality {
+    Low,
+    High,
+}
+
+#[derive(CreateTable)]
+#[table_name(video)]
+struct VideoEntry {
+    #[primary]
+    name: String,
+    #[text]
+    quality: Quality,
+}
+
+fn main() {}
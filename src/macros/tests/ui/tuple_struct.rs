@@ -0,0 +1,7 @@
+use macros::*;
+
+#[derive(Insert)]
+#[table_name(video)]
+struct VideoEntry(String, u32);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use macros::*;
+
+#[derive(Select)]
+#[table_name(video)]
+struct VideoEntry {
+    #[column(rename = "video_name")]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use macros::*;
+use std::collections::HashMap;
+
+#[derive(CreateTable)]
+#[table_name(video)]
+struct VideoEntry {
+    name: String,
+    attrs: HashMap<String, String>,
+}
+
+fn main() {}
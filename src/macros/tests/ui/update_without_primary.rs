@@ -0,0 +1,10 @@
+use macros::*;
+
+#[derive(Update)]
+#[table_name(video_tag)]
+struct VideoTagEntry {
+    video: String,
+    tag: String,
+}
+
+fn main() {}
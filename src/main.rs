@@ -3,10 +3,15 @@ use ffmpeg_next as ffmpeg;
 use ffmpeg_next::media::Type;
 use macros::*;
 use rand::seq::SliceRandom;
+use runtime::dsl::{Condition, Order, Query};
 use rusqlite::OptionalExtension;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::FromStr;
 
+mod migration;
+mod naming;
+mod query;
+
 #[derive(Parser)]
 #[clap(setting(AppSettings::DisableHelpSubcommand))]
 #[clap(setting(AppSettings::DeriveDisplayOrder))]
@@ -17,15 +22,83 @@ struct Arg {
 
 #[derive(clap::Subcommand)]
 enum Command {
-    #[clap(about = "Add the files in './pending/' into management")]
-    Add,
+    #[clap(about = "Add the files in './pending/' and its folders into management")]
+    Add {
+        #[clap(flatten)]
+        arg: AddArg,
+    },
 
-    #[clap(about = "Tag the video with given word")]
+    #[clap(about = "Manage the tags of the video(s)")]
     Tag {
-        #[clap(help = "The video's name (extension part and dash character removed name)")]
+        #[clap(subcommand)]
+        command: TagCommand,
+    },
+
+    #[clap(about = "Manage the tags across all videos")]
+    Tags {
+        #[clap(subcommand)]
+        command: TagsCommand,
+    },
+
+    #[clap(about = "Manage the aliases of the tags")]
+    Alias {
+        #[clap(subcommand)]
+        command: AliasCommand,
+    },
+
+    #[clap(about = "Rate the video with 0 to 5 stars")]
+    Rate {
+        #[clap(help = "The name of the video")]
         name: String,
-        #[clap(help = "The word to tag the video")]
-        tag: String,
+        #[clap(value_parser = clap::value_parser!(u32).range(0..=5))]
+        #[clap(help = "The rating of the video")]
+        rating: u32,
+    },
+
+    #[clap(about = "Mark the video as favorite")]
+    Fav {
+        #[clap(help = "The name of the video")]
+        name: String,
+        #[clap(short, long)]
+        #[clap(help = "Unmark the video as favorite instead")]
+        remove: bool,
+    },
+
+    #[clap(about = "Edit the notes of the video with $EDITOR")]
+    Note {
+        #[clap(help = "The name of the video")]
+        name: String,
+    },
+
+    #[clap(about = "Manage the collections of videos")]
+    Collection {
+        #[clap(subcommand)]
+        command: CollectionCommand,
+    },
+
+    #[clap(about = "Manage the persons appearing in the videos")]
+    Person {
+        #[clap(subcommand)]
+        command: PersonCommand,
+    },
+
+    #[clap(about = "Set the custom attribute of the video")]
+    Set {
+        #[clap(help = "The name of the video")]
+        name: String,
+        #[clap(help = "The key of the attribute")]
+        key: String,
+        #[clap(help = "The value of the attribute")]
+        value: String,
+    },
+
+    #[clap(about = "Unset the custom attribute(s) of the video")]
+    Unset {
+        #[clap(help = "The name of the video")]
+        name: String,
+        #[clap(required = true)]
+        #[clap(help = "The key(s) of the attribute(s)")]
+        keys: Vec<String>,
     },
 
     #[clap(about = "Play the video")]
@@ -34,6 +107,22 @@ enum Command {
         name: String,
     },
 
+    #[clap(about = "Show the recently played video(s)")]
+    History {
+        #[clap(help = "Only show the plays of the video")]
+        name: Option<String>,
+
+        #[clap(short, long)]
+        #[clap(default_value = "20")]
+        #[clap(help = "Limit the total number of shown play(s)")]
+        limit: u64,
+
+        #[clap(short, long)]
+        #[clap(default_value = "0")]
+        #[clap(help = "Skip the <OFFSET> most recent play(s)")]
+        offset: u64,
+    },
+
     #[clap(about = "List the video(s) filtered by given condition(s)")]
     List {
         #[clap(flatten)]
@@ -51,6 +140,12 @@ enum Command {
     #[clap(about = "Clean './links/'")]
     Clean,
 
+    #[clap(about = "Manage the database")]
+    Db {
+        #[clap(subcommand)]
+        command: DbCommand,
+    },
+
     #[clap(about = "Check the inconsistency between file system and database")]
     Check {
         #[clap(short, long)]
@@ -59,6 +154,260 @@ enum Command {
     },
 }
 
+#[derive(clap::Subcommand)]
+enum TagCommand {
+    #[clap(about = "Tag the video with given word(s)")]
+    Add {
+        #[clap(help = "The video's name (extension part and dash character removed name)")]
+        name: String,
+        #[clap(required = true)]
+        #[clap(
+            help = "The word(s) to tag the video; Accept an optional namespace like 'actor:NAME'"
+        )]
+        tags: Vec<String>,
+    },
+
+    #[clap(about = "Remove given word(s) from the tags of the video")]
+    Remove {
+        #[clap(help = "The name of the video")]
+        name: String,
+        #[clap(required = true)]
+        #[clap(help = "The word(s) to remove from the video")]
+        tags: Vec<String>,
+    },
+
+    #[clap(about = "List the tags of the video; List all tags if no video is given")]
+    List {
+        #[clap(help = "The name of the video")]
+        name: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum TagsCommand {
+    #[clap(about = "List all tags with their video count and total duration")]
+    List,
+
+    #[clap(about = "Rename the tag on all videos")]
+    Rename {
+        #[clap(help = "The tag to rename")]
+        from: String,
+        #[clap(help = "The new name of the tag")]
+        to: String,
+    },
+
+    #[clap(about = "Merge the tag(s) into another tag on all videos")]
+    Merge {
+        #[clap(required = true)]
+        #[clap(help = "The tag(s) to merge")]
+        tags: Vec<String>,
+        #[clap(long)]
+        #[clap(help = "The tag which the tag(s) are merged into")]
+        into: String,
+    },
+
+    #[clap(about = "Delete the tag from all videos")]
+    Delete {
+        #[clap(help = "The tag to delete")]
+        tag: String,
+    },
+
+    #[clap(about = "Show all tags as a tree with their video count")]
+    Tree,
+
+    #[clap(
+        about = "Set the parent of the tag; Unset it if no parent is given. Tags named like 'PARENT/CHILD' are renamed instead"
+    )]
+    Parent {
+        #[clap(help = "The child tag")]
+        tag: String,
+        #[clap(help = "The parent tag")]
+        parent: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum AliasCommand {
+    #[clap(about = "Declare the word(s) as alias(es) of the tag")]
+    Add {
+        #[clap(help = "The canonical tag")]
+        tag: String,
+        #[clap(required = true)]
+        #[clap(
+            help = "The alias(es) of the tag; Videos tagged by them are retagged by the canonical tag"
+        )]
+        aliases: Vec<String>,
+    },
+
+    #[clap(about = "Remove the alias(es)")]
+    Remove {
+        #[clap(required = true)]
+        #[clap(help = "The alias(es) to remove")]
+        aliases: Vec<String>,
+    },
+
+    #[clap(about = "List the aliases of the tag; List all aliases if no tag is given")]
+    List {
+        #[clap(help = "The canonical tag")]
+        tag: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum CollectionCommand {
+    #[clap(about = "Create a collection")]
+    Create {
+        #[clap(help = "The name of the collection")]
+        name: String,
+    },
+
+    #[clap(
+        about = "Add the video into the collection; Move it if it is already in the collection"
+    )]
+    Add {
+        #[clap(help = "The name of the collection")]
+        collection: String,
+        #[clap(help = "The name of the video")]
+        name: String,
+        #[clap(short, long)]
+        #[clap(
+            help = "The position of the video in the collection, starting from 1; Append it if not given"
+        )]
+        position: Option<u32>,
+    },
+
+    #[clap(about = "Remove the video from the collection")]
+    Remove {
+        #[clap(help = "The name of the collection")]
+        collection: String,
+        #[clap(help = "The name of the video")]
+        name: String,
+    },
+
+    #[clap(
+        about = "Show the videos of the collection in order; Show all collections if no collection is given"
+    )]
+    Show {
+        #[clap(help = "The name of the collection")]
+        collection: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum PersonCommand {
+    #[clap(about = "Create a person")]
+    Add {
+        #[clap(help = "The name of the person")]
+        name: String,
+        #[clap(short, long)]
+        #[clap(help = "The alias of the person; Can be given multiple times")]
+        alias: Vec<String>,
+    },
+
+    #[clap(about = "Delete the person and unlink all videos from it")]
+    Remove {
+        #[clap(help = "The name or alias of the person")]
+        name: String,
+    },
+
+    #[clap(about = "Add aliases to the person")]
+    Alias {
+        #[clap(help = "The name or alias of the person")]
+        name: String,
+        #[clap(required = true)]
+        #[clap(help = "The alias(es) of the person")]
+        aliases: Vec<String>,
+        #[clap(short, long)]
+        #[clap(help = "Remove the alias(es) instead")]
+        remove: bool,
+    },
+
+    #[clap(about = "Edit the notes of the person with $EDITOR")]
+    Note {
+        #[clap(help = "The name or alias of the person")]
+        name: String,
+    },
+
+    #[clap(about = "Copy the image into './portraits/' as the portrait of the person")]
+    Portrait {
+        #[clap(help = "The name or alias of the person")]
+        name: String,
+        #[clap(help = "The path of the image; Clear the portrait if not given")]
+        file: Option<String>,
+    },
+
+    #[clap(about = "Link the video(s) to the person")]
+    Link {
+        #[clap(help = "The name or alias of the person")]
+        person: String,
+        #[clap(required = true)]
+        #[clap(help = "The name(s) of the video(s)")]
+        names: Vec<String>,
+    },
+
+    #[clap(about = "Unlink the video(s) from the person")]
+    Unlink {
+        #[clap(help = "The name or alias of the person")]
+        person: String,
+        #[clap(required = true)]
+        #[clap(help = "The name(s) of the video(s)")]
+        names: Vec<String>,
+    },
+
+    #[clap(about = "List all persons")]
+    List,
+
+    #[clap(about = "Show the summary of the person")]
+    Show {
+        #[clap(help = "The name or alias of the person")]
+        name: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum DbCommand {
+    #[clap(about = "Apply the pending migrations of the database")]
+    Migrate {
+        #[clap(long)]
+        #[clap(help = "Print the SQL of the pending migrations without applying them")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Args)]
+#[clap(setting(AppSettings::DeriveDisplayOrder))]
+struct AddArg {
+    #[clap(short, long)]
+    #[clap(
+        help = "Scan the folders in './pending/' down to <DEPTH> level(s); 1 means only the files directly in it; No limit if not given"
+    )]
+    depth: Option<usize>,
+
+    #[clap(short, long)]
+    #[clap(
+        help = "Only add the files whose names match the glob <INCLUDE> like '*.mp4', ignoring case; Can be given multiple times"
+    )]
+    include: Vec<String>,
+
+    #[clap(short = 'x', long)]
+    #[clap(
+        help = "Skip the files whose names match the glob <EXCLUDE> like '*.part', ignoring case; Can be given multiple times"
+    )]
+    exclude: Vec<String>,
+
+    #[clap(short = 't', long)]
+    #[clap(
+        help = "Tag the added video(s) with the paths of their folders in './pending/', like 'outdoor/beach' as 'OUTDOOR/BEACH'"
+    )]
+    folder_tags: bool,
+
+    #[clap(short, long)]
+    #[clap(
+        help = "Show how the names of the files are extracted by the rules in './name_rules.txt' instead of adding them"
+    )]
+    explain: bool,
+}
+
 #[derive(Args)]
 #[clap(setting(AppSettings::DeriveDisplayOrder))]
 struct FilterArg {
@@ -67,8 +416,16 @@ struct FilterArg {
     name: Option<String>,
 
     #[clap(short, long)]
-    #[clap(help = "The filtered video(s) should be tagged by <TAG>")]
-    tag: Option<String>,
+    #[clap(
+        help = "The filtered video(s) should be tagged by <TAG>; Can be given multiple times; 'NAMESPACE:' matches any tag in the namespace"
+    )]
+    tag: Vec<String>,
+
+    #[clap(short, long)]
+    #[clap(
+        help = "The filtered video(s) should match the expression <QUERY>; e.g. '(OUTDOOR or BEACH) and not WATCHED and duration>30:00'"
+    )]
+    query: Option<String>,
 
     #[clap(short, long)]
     #[clap(
@@ -81,10 +438,67 @@ struct FilterArg {
     #[clap(help = "The error in second(s) when filtering videos by duration")]
     duration_range: usize,
 
+    #[clap(short = 'r', long)]
+    #[clap(default_value = "0")]
+    #[clap(help = "The rating of filtered video(s) should be at least <MIN_RATING>")]
+    min_rating: u32,
+
+    #[clap(short, long)]
+    #[clap(help = "The filtered video(s) should be marked as favorite")]
+    favorites: bool,
+
+    #[clap(short = 'p', long)]
+    #[clap(
+        help = "The filtered video(s) should not be played within <AGE>; Accept formats like '90s', '30m', '12h', '30d', '2w'"
+    )]
+    not_played_since: Option<AgeArg>,
+
+    #[clap(short, long)]
+    #[clap(
+        help = "The custom attribute of filtered video(s) should match <ATTR>; Can be given multiple times; Accept formats like 'KEY=VALUE', 'KEY~GLOB', 'KEY>NUMBER', 'KEY<NUMBER'"
+    )]
+    attr: Vec<AttrArg>,
+
+    #[clap(short = 'P', long)]
+    #[clap(
+        help = "The filtered video(s) should be linked to the person <PERSON>; Can be given multiple times"
+    )]
+    person: Vec<String>,
+
+    #[clap(short, long)]
+    #[clap(help = "The filtered video(s) should be in the collection <COLLECTION>")]
+    collection: Option<String>,
+
+    #[clap(short = 'S', long)]
+    #[clap(help = "The notes of filtered video(s) should match the full-text query <SEARCH>")]
+    search: Option<String>,
+
+    #[clap(short, long, value_enum)]
+    #[clap(
+        help = "Sort the filtered video(s) by <SORT>; Default to 'relevance' when searching, 'position' when filtering by collection, otherwise 'name'"
+    )]
+    sort: Option<SortKey>,
+
     #[clap(short, long)]
     #[clap(default_value = "0")]
     #[clap(help = "Limit the total number of filtered video(s); 0 means no limit")]
     limit: usize,
+
+    #[clap(short, long)]
+    #[clap(
+        help = "Pick the video(s) randomly by a weight of their rating plus one instead of uniformly when limited"
+    )]
+    weighted: bool,
+}
+
+#[derive(Clone, clap::ValueEnum)]
+enum SortKey {
+    Name,
+    Rating,
+    LastPlayed,
+    PlayCount,
+    Relevance,
+    Position,
 }
 
 struct DurationArg(usize);
@@ -104,124 +518,555 @@ impl FromStr for DurationArg {
     }
 }
 
-#[derive(Default, CreateTable, CreateIndex, Select, Insert)]
+struct AttrArg {
+    key: String,
+    op: &'static str,
+    value: String,
+}
+impl FromStr for AttrArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let i = match s.find(['=', '~', '<', '>']) {
+            Some(i) => i,
+            None => return Err(String::from("missing operator '=', '~', '>' or '<'")),
+        };
+        let key = s[..i].trim().to_ascii_lowercase();
+        if key.is_empty() {
+            return Err(String::from("missing key"));
+        }
+        let rest = &s[i..];
+        let op = ["=", "~", ">=", "<=", ">", "<"]
+            .into_iter()
+            .find(|op| rest.starts_with(op))
+            .unwrap();
+        let value = rest[op.len()..].to_string();
+        if matches!(op, ">=" | "<=" | ">" | "<") && !value.parse::<f64>().is_ok_and(f64::is_finite)
+        {
+            return Err(format!("'{value}' is not a number"));
+        }
+        Ok(Self { key, op, value })
+    }
+}
+
+struct AgeArg(i64);
+impl FromStr for AgeArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unit = match s.chars().last() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            Some('w') => 7 * 24 * 60 * 60,
+            _ => return Err(String::from("missing unit 's', 'm', 'h', 'd' or 'w'")),
+        };
+        let n: i64 = s[..s.len() - 1].parse().map_err(|e| format!("{e}"))?;
+        if n < 0 {
+            return Err(String::from("negative age"));
+        }
+        match n.checked_mul(unit) {
+            Some(seconds) => Ok(Self(seconds)),
+            None => Err(format!("age '{s}' is too large")),
+        }
+    }
+}
+
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Upsert, Update, Delete)]
 #[table_name(video)]
 struct VideoEntry {
     #[primary]
     name: String,
-    #[index]
-    tag: String,
     file_name: String,
-    file_size: u32,
+    file_size: u64,
     #[index]
-    duration: u32,
+    duration: u64,
     video_codec: String,
     video_bit_rate: u32,
-    video_frame_rate: u32,
+    video_frame_rate: f64,
     video_width: u32,
     video_height: u32,
     audio_codec: String,
     audio_bit_rate: u32,
+    #[index]
+    #[column(keep)]
+    rating: u32,
+    #[column(keep)]
+    favorite: bool,
+    #[column(keep)]
+    play_count: u32,
+    #[index]
+    #[column(keep)]
+    last_played: i64,
 }
 
 #[derive(Default, Select)]
 #[table_name(video)]
 struct BriefVideoEntry {
     name: String,
-    tag: String,
     file_name: String,
-    file_size: u32,
-    duration: u32,
+    file_size: u64,
+    duration: u64,
+    rating: u32,
+    favorite: bool,
 }
 
-static mut DB_CONNECTION: Option<rusqlite::Connection> = None;
-
-fn main() {
-    let arg = Arg::parse();
-    match arg.command {
-        Command::Add => do_add(),
-        Command::Tag { name, tag } => do_tag(name, tag),
-        Command::Play { name } => do_play(name),
-        Command::List { arg, verbose, link } => do_list(arg, verbose, link),
-        Command::Clean => do_clean(),
-        Command::Check { fix } => do_check(fix),
-    }
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(play_event)]
+struct PlayEventEntry {
+    #[index]
+    #[references(video.name)]
+    video: String,
+    #[index]
+    played_at: i64,
 }
 
-#[inline]
-fn db_connection() -> &'static rusqlite::Connection {
-    unsafe { DB_CONNECTION.as_ref().unwrap() }
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(video_attr)]
+#[unique(name, key)]
+struct VideoAttrEntry {
+    #[references(video.name)]
+    name: String,
+    #[index]
+    key: String,
+    value: String,
 }
 
-fn prepare_environments() {
-    let path = std::env::current_exe().unwrap();
-    let path = path.parent().unwrap();
-    std::env::set_current_dir(path).unwrap();
-
-    prepare_directory("files");
-    prepare_directory("links");
-    prepare_directory("pending");
-    let db_connection = prepare_database();
-    unsafe { DB_CONNECTION = Some(db_connection) };
+#[derive(Default, CreateTable, Select)]
+#[table_name(collection)]
+struct CollectionEntry {
+    #[primary]
+    name: String,
 }
 
-fn prepare_directory(dir_name: &str) {
-    match std::fs::metadata(dir_name) {
-        Ok(md) => {
-            if !md.is_dir() {
-                panic!("'./{dir_name}' exists but is not a directory")
-            }
-        }
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            std::fs::create_dir(dir_name).unwrap();
-        }
-        Err(e) => {
-            panic!("failed to prepare directory: {e}");
-        }
-    };
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(collection_video)]
+#[unique(collection, video)]
+#[index(collection, position)]
+struct CollectionVideoEntry {
+    #[references(collection.name)]
+    collection: String,
+    #[index]
+    #[references(video.name)]
+    video: String,
+    position: u32,
 }
 
-fn prepare_database() -> rusqlite::Connection {
-    let c = rusqlite::Connection::open("database").unwrap();
-    VideoEntry::create_table(&c).unwrap();
-    VideoEntry::create_indexes(&c).unwrap();
-    c
+#[derive(Default, CreateTable, Select, Insert, Update, Delete)]
+#[table_name(person)]
+struct PersonEntry {
+    #[primary]
+    name: String,
+    note: String,
+    portrait: String,
 }
 
-fn prepare_where_clause(arg: &FilterArg) -> (String, Vec<String>) {
-    let mut exprs = vec![];
-    let mut params = vec![];
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(person_alias)]
+struct PersonAliasEntry {
+    #[primary]
+    alias: String,
+    #[index]
+    #[references(person.name)]
+    person: String,
+}
+
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(video_person)]
+#[unique(video, person)]
+struct VideoPersonEntry {
+    #[references(video.name)]
+    video: String,
+    #[index]
+    #[references(person.name)]
+    person: String,
+}
+
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(tag)]
+struct TagEntry {
+    #[primary]
+    name: String,
+    #[index]
+    namespace: String,
+}
+
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(tag_alias)]
+struct TagAliasEntry {
+    #[primary]
+    alias: String,
+    #[index]
+    #[references(tag.name)]
+    tag: String,
+}
+
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(tag_parent)]
+struct TagParentEntry {
+    #[primary]
+    #[references(tag.name)]
+    tag: String,
+    #[index]
+    #[references(tag.name)]
+    parent: String,
+}
+
+#[derive(Default, CreateTable, CreateIndex, Select, Insert, Delete)]
+#[table_name(video_tag)]
+#[unique(video, tag)]
+struct VideoTagEntry {
+    #[references(video.name)]
+    video: String,
+    #[index]
+    #[references(tag.name)]
+    tag: String,
+}
+
+fn tag_condition(tag: &str) -> Condition {
+    match tag.strip_suffix(':') {
+        Some(namespace) => Condition::new(
+            "name IN (SELECT video FROM video_tag JOIN tag ON tag.name=video_tag.tag WHERE tag.namespace=?)",
+        )
+        .bind(namespace.to_string()),
+        None => Condition::new(
+            "name IN (SELECT video FROM video_tag WHERE tag IN (\
+            WITH RECURSIVE descendant(name) AS (\
+            SELECT IFNULL((SELECT tag FROM tag_alias WHERE alias=?), ?) \
+            UNION SELECT tag_parent.tag FROM tag_parent JOIN descendant ON tag_parent.parent=descendant.name) \
+            SELECT name FROM descendant))",
+        )
+        .bind(tag.to_string())
+        .bind(tag.to_string()),
+    }
+}
+
+fn tag_namespace(tag: &str) -> &str {
+    match tag.split_once(':') {
+        Some((namespace, _)) => namespace,
+        None => "",
+    }
+}
+
+static mut DB_CONNECTION: Option<rusqlite::Connection> = None;
+
+fn main() {
+    let arg = Arg::parse();
+    match arg.command {
+        Command::Add { arg } => do_add(arg),
+        Command::Tag { command } => match command {
+            TagCommand::Add { name, tags } => do_tag_add(name, tags),
+            TagCommand::Remove { name, tags } => do_tag_remove(name, tags),
+            TagCommand::List { name } => do_tag_list(name),
+        },
+        Command::Tags { command } => match command {
+            TagsCommand::List => do_tags_list(),
+            TagsCommand::Rename { from, to } => do_tags_rename(from, to),
+            TagsCommand::Merge { tags, into } => do_tags_merge(tags, into),
+            TagsCommand::Delete { tag } => do_tags_delete(tag),
+            TagsCommand::Tree => do_tags_tree(),
+            TagsCommand::Parent { tag, parent } => do_tags_parent(tag, parent),
+        },
+        Command::Alias { command } => match command {
+            AliasCommand::Add { tag, aliases } => do_alias_add(tag, aliases),
+            AliasCommand::Remove { aliases } => do_alias_remove(aliases),
+            AliasCommand::List { tag } => do_alias_list(tag),
+        },
+        Command::Rate { name, rating } => do_rate(name, rating),
+        Command::Fav { name, remove } => do_fav(name, !remove),
+        Command::Note { name } => do_note(name),
+        Command::Collection { command } => match command {
+            CollectionCommand::Create { name } => do_collection_create(name),
+            CollectionCommand::Add {
+                collection,
+                name,
+                position,
+            } => do_collection_add(collection, name, position),
+            CollectionCommand::Remove { collection, name } => {
+                do_collection_remove(collection, name)
+            }
+            CollectionCommand::Show { collection } => do_collection_show(collection),
+        },
+        Command::Person { command } => match command {
+            PersonCommand::Add { name, alias } => do_person_add(name, alias),
+            PersonCommand::Remove { name } => do_person_remove(name),
+            PersonCommand::Alias {
+                name,
+                aliases,
+                remove,
+            } => do_person_alias(name, aliases, remove),
+            PersonCommand::Note { name } => do_person_note(name),
+            PersonCommand::Portrait { name, file } => do_person_portrait(name, file),
+            PersonCommand::Link { person, names } => do_person_link(person, names),
+            PersonCommand::Unlink { person, names } => do_person_unlink(person, names),
+            PersonCommand::List => do_person_list(),
+            PersonCommand::Show { name } => do_person_show(name),
+        },
+        Command::Set { name, key, value } => do_set(name, key, value),
+        Command::Unset { name, keys } => do_unset(name, keys),
+        Command::Play { name } => do_play(name),
+        Command::History {
+            name,
+            limit,
+            offset,
+        } => do_history(name, limit, offset),
+        Command::List { arg, verbose, link } => do_list(arg, verbose, link),
+        Command::Clean => do_clean(),
+        Command::Db { command } => match command {
+            DbCommand::Migrate { dry_run } => do_db_migrate(dry_run),
+        },
+        Command::Check { fix } => do_check(fix),
+    }
+}
+
+#[inline]
+fn db_connection() -> &'static rusqlite::Connection {
+    unsafe { (*std::ptr::addr_of!(DB_CONNECTION)).as_ref().unwrap() }
+}
+
+fn prepare_environments() {
+    enter_program_directory();
+    prepare_directory("files");
+    prepare_directory("links");
+    prepare_directory("pending");
+    prepare_directory("portraits");
+    let db_connection = prepare_database();
+    unsafe { DB_CONNECTION = Some(db_connection) };
+}
+
+fn enter_program_directory() {
+    let path = std::env::current_exe().unwrap();
+    let path = path.parent().unwrap();
+    std::env::set_current_dir(path).unwrap();
+}
+
+fn prepare_directory(dir_name: &str) {
+    match std::fs::metadata(dir_name) {
+        Ok(md) => {
+            if !md.is_dir() {
+                panic!("'./{dir_name}' exists but is not a directory")
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            std::fs::create_dir(dir_name).unwrap();
+        }
+        Err(e) => {
+            panic!("failed to prepare directory: {e}");
+        }
+    };
+}
+
+fn prepare_database() -> rusqlite::Connection {
+    let mut c = rusqlite::Connection::open("database").unwrap();
+    let version = migration::current_version(&c).unwrap();
+    let latest = migration::latest_version();
+    let is_new: bool = c
+        .query_row("SELECT COUNT(*)=0 FROM sqlite_master", [], |row| row.get(0))
+        .unwrap();
+    // Only a new database is migrated implicitly; the others may lose data, so it is left to
+    // `db migrate`, which can be previewed with `--dry-run`.
+    if is_new {
+        if let Err(e) = migration::migrate(&mut c) {
+            panic!("{e}");
+        }
+    } else if version < latest {
+        eprintln!(
+            "database is at version {version}, run `db migrate` to upgrade it to version {latest}"
+        );
+        std::process::exit(1);
+    } else if version > latest {
+        eprintln!("database is at version {version}, newer than the supported version {latest}");
+        std::process::exit(1);
+    }
+    match sync_schemas(&mut c) {
+        Ok(diffs) => {
+            for diff in diffs {
+                for problem in diff.problems() {
+                    eprintln!("{problem}; a migration is needed");
+                }
+            }
+        }
+        Err(e) => panic!("failed to synchronize schema: {e}"),
+    }
+    if let Err(e) = create_indexes(&mut c) {
+        panic!("failed to create indexes: {e}");
+    }
+    // Enabled after migrating, since rebuilding a table would delete the rows referencing it.
+    c.execute_batch("PRAGMA foreign_keys=ON").unwrap();
+    c
+}
+
+/// Create the missing tables and columns of the entries.
+fn sync_schemas(c: &mut rusqlite::Connection) -> rusqlite::Result<Vec<runtime::SchemaDiff>> {
+    let tx = c.transaction()?;
+    let diffs = vec![
+        VideoEntry::sync_schema(&tx)?,
+        PlayEventEntry::sync_schema(&tx)?,
+        VideoAttrEntry::sync_schema(&tx)?,
+        CollectionEntry::sync_schema(&tx)?,
+        CollectionVideoEntry::sync_schema(&tx)?,
+        PersonEntry::sync_schema(&tx)?,
+        PersonAliasEntry::sync_schema(&tx)?,
+        VideoPersonEntry::sync_schema(&tx)?,
+        TagEntry::sync_schema(&tx)?,
+        TagAliasEntry::sync_schema(&tx)?,
+        TagParentEntry::sync_schema(&tx)?,
+        VideoTagEntry::sync_schema(&tx)?,
+    ];
+    tx.commit()?;
+    Ok(diffs)
+}
+
+fn create_indexes(c: &mut rusqlite::Connection) -> rusqlite::Result<()> {
+    let tx = c.transaction()?;
+    VideoEntry::create_indexes(&tx)?;
+    PlayEventEntry::create_indexes(&tx)?;
+    VideoAttrEntry::create_indexes(&tx)?;
+    CollectionVideoEntry::create_indexes(&tx)?;
+    PersonAliasEntry::create_indexes(&tx)?;
+    VideoPersonEntry::create_indexes(&tx)?;
+    TagEntry::create_indexes(&tx)?;
+    TagAliasEntry::create_indexes(&tx)?;
+    TagParentEntry::create_indexes(&tx)?;
+    VideoTagEntry::create_indexes(&tx)?;
+    tx.commit()
+}
+
+fn query_video_tags() -> rusqlite::Result<HashMap<String, Vec<String>>> {
+    let entries = VideoTagEntry::select(db_connection(), "ORDER BY video, tag", [])?;
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in entries {
+        tags.entry(entry.video).or_default().push(entry.tag);
+    }
+    Ok(tags)
+}
+
+fn prepare_query(arg: &FilterArg) -> Result<Query, String> {
+    let cols = VideoEntry::cols();
+    let mut query = Query::new();
 
     if let Some(name) = &arg.name {
         let name = name.to_ascii_uppercase();
-        exprs.push("name GLOB ?");
-        params.push(format!("{name}*"));
+        query = query.filter(cols.name.glob(format!("{name}*")));
     }
-    if let Some(tag) = &arg.tag {
+    for tag in &arg.tag {
         let tag = tag.to_ascii_uppercase();
-        exprs.push("tag=?");
-        params.push(tag.clone());
+        query = query.filter(tag_condition(&tag));
     }
     if let Some(duration) = &arg.duration {
         let min = usize::saturating_sub(duration.0, arg.duration_range);
         let max = usize::saturating_add(duration.0, arg.duration_range);
-        exprs.push("duration BETWEEN ? AND ?");
-        params.push(min.to_string());
-        params.push(max.to_string());
+        query = query.filter(cols.duration.between(min as u64, max as u64));
+    }
+    if arg.min_rating > 0 {
+        query = query.filter(cols.rating.ge(arg.min_rating));
+    }
+    if arg.favorites {
+        query = query.filter(cols.favorite.is_true());
+    }
+    if let Some(age) = &arg.not_played_since {
+        query = query.filter(cols.last_played.lt(unix_timestamp().saturating_sub(age.0)));
+    }
+    if let Some(source) = &arg.query {
+        let expr = query::parse(source)
+            .map_err(|e| format!("failed to parse query: {}", e.report(source)))?;
+        query = query.filter(expr.to_condition());
     }
 
-    let clause = if exprs.is_empty() {
-        String::from("ORDER BY name")
-    } else {
-        let mut s = String::from("WHERE ");
-        s.push_str(&exprs.join(" AND "));
-        s.push_str(" ORDER BY name");
-        s
+    for attr in &arg.attr {
+        let expr = match attr.op {
+            "=" => String::from("name IN (SELECT name FROM video_attr WHERE key=? AND value=?)"),
+            "~" => {
+                String::from("name IN (SELECT name FROM video_attr WHERE key=? AND value GLOB ?)")
+            }
+            // Only the numeric values are compared, since the others are cast into 0.
+            op => format!(
+                "name IN (SELECT name FROM video_attr WHERE key=? \
+                AND (CAST(value AS INTEGER)||''=value OR CAST(value AS REAL)||''=value) \
+                AND CAST(value AS REAL){op}CAST(? AS REAL))"
+            ),
+        };
+        let condition = Condition::new(expr)
+            .bind(attr.key.clone())
+            .bind(attr.value.clone());
+        query = query.filter(condition);
+    }
+    for person in &arg.person {
+        let person = person.to_ascii_uppercase();
+        let condition = Condition::new(
+            "name IN (SELECT video FROM video_person WHERE person=IFNULL((SELECT person FROM person_alias WHERE alias=?), ?))",
+        )
+        .bind(person.clone())
+        .bind(person);
+        query = query.filter(condition);
+    }
+    if let Some(collection) = &arg.collection {
+        let condition =
+            Condition::new("name IN (SELECT video FROM collection_video WHERE collection=?)")
+                .bind(collection.clone());
+        query = query.filter(condition);
+    }
+    if let Some(search) = &arg.search {
+        let condition =
+            Condition::new("name IN (SELECT video FROM video_note WHERE video_note MATCH ?)")
+                .bind(search.clone());
+        query = query.filter(condition);
+    }
+
+    let sort = match (&arg.sort, &arg.search, &arg.collection) {
+        (Some(sort), _, _) => sort.clone(),
+        (None, Some(_), _) => SortKey::Relevance,
+        (None, None, Some(_)) => SortKey::Position,
+        (None, None, None) => SortKey::Name,
+    };
+    let order = match sort {
+        SortKey::Name => None,
+        SortKey::Rating => Some(cols.rating.desc()),
+        SortKey::LastPlayed => Some(cols.last_played.desc()),
+        SortKey::PlayCount => Some(cols.play_count.desc()),
+        SortKey::Relevance => match &arg.search {
+            Some(search) => Some(
+                Order::new("(SELECT bm25(video_note) FROM video_note WHERE video_note MATCH ? AND video=name)")
+                    .bind(search.clone()),
+            ),
+            None => {
+                return Err(String::from(
+                    "failed to sort by relevance; option '--search' is not given",
+                ))
+            }
+        },
+        SortKey::Position => match &arg.collection {
+            Some(collection) => Some(
+                Order::new("(SELECT position FROM collection_video WHERE collection=? AND video=name)")
+                    .bind(collection.clone()),
+            ),
+            None => {
+                return Err(String::from(
+                    "failed to sort by position; option '--collection' is not given",
+                ))
+            }
+        },
     };
-    (clause, params)
+    if let Some(order) = order {
+        query = query.order_by(order);
+    }
+    Ok(query.order_by(cols.name.asc()))
+}
+
+fn group_tags_by_namespace(tags: &[String]) -> BTreeMap<&str, Vec<&str>> {
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for tag in tags {
+        let (namespace, tag) = match tag.split_once(':') {
+            Some((namespace, tag)) => (namespace, tag),
+            None => ("", tag.as_str()),
+        };
+        groups.entry(namespace).or_default().push(tag);
+    }
+    groups
 }
 
-fn readable_file_size(file_size: u32) -> String {
+fn readable_file_size(file_size: u64) -> String {
     let mut size = file_size;
     let mut unit = "B";
     let mut unit_changed = false;
@@ -235,6 +1080,10 @@ fn readable_file_size(file_size: u32) -> String {
             if size > 1024 {
                 size /= 1024;
                 unit = "GB";
+                if size > 1024 {
+                    size /= 1024;
+                    unit = "TB";
+                }
             }
         }
     }
@@ -245,7 +1094,7 @@ fn readable_file_size(file_size: u32) -> String {
     }
 }
 
-fn readable_duration(duration: u32) -> String {
+fn readable_duration(duration: u64) -> String {
     let mut seconds = duration;
     let mut minutes = 0;
     let mut hours = 0;
@@ -264,6 +1113,72 @@ fn readable_duration(duration: u32) -> String {
     }
 }
 
+fn readable_frame_rate(frame_rate: f64) -> String {
+    let frame_rate = (frame_rate * 100.0).round() / 100.0;
+    frame_rate.to_string()
+}
+
+fn readable_age(timestamp: i64) -> String {
+    if timestamp <= 0 {
+        return String::from("never");
+    }
+    let seconds = unix_timestamp() - timestamp;
+    let (n, unit) = if seconds >= 24 * 60 * 60 {
+        (seconds / (24 * 60 * 60), "d")
+    } else if seconds >= 60 * 60 {
+        (seconds / (60 * 60), "h")
+    } else if seconds >= 60 {
+        (seconds / 60, "m")
+    } else {
+        (seconds, "s")
+    };
+    format!("{n}{unit} ago")
+}
+
+fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as _
+}
+
+fn readable_rating(rating: u32, favorite: bool) -> String {
+    let mut s = String::new();
+    if rating > 0 {
+        s.push_str(" rating=");
+        s.push_str(&rating.to_string());
+    }
+    if favorite {
+        s.push_str(" favorite");
+    }
+    s
+}
+
+fn pick_randomly<T, F: Fn(&T) -> u32>(
+    entries: Vec<T>,
+    limit: usize,
+    weighted: bool,
+    rating: F,
+) -> Vec<T> {
+    let mut rng = rand::thread_rng();
+    let indices: Vec<usize> = (0..entries.len()).collect();
+    let picked: HashSet<usize> = if weighted {
+        indices
+            .choose_multiple_weighted(&mut rng, limit, |i| rating(&entries[*i]) as f64 + 1.0)
+            .unwrap()
+            .copied()
+            .collect()
+    } else {
+        indices.choose_multiple(&mut rng, limit).copied().collect()
+    };
+    entries
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picked.contains(i))
+        .map(|(_, e)| e)
+        .collect()
+}
+
 fn clear_directory(dir: &str) -> std::io::Result<()> {
     let read_dir = std::fs::read_dir(dir)?;
     for entry in read_dir {
@@ -279,96 +1194,315 @@ fn clear_directory(dir: &str) -> std::io::Result<()> {
 }
 
 #[cfg(windows)]
-fn make_link(file_name: &str) -> std::io::Result<()> {
+fn make_link(file_name: &str, link_name: &str) -> std::io::Result<()> {
     let mut src = std::env::current_dir().unwrap();
     let mut dst = src.clone();
     src.push("files");
     src.push(file_name);
     dst.push("links");
-    dst.push(file_name);
+    dst.push(link_name);
     std::os::windows::fs::symlink_file(src, dst)
 }
 
 #[cfg(not(windows))]
-fn make_link(file_name: &str) -> std::io::Result<()> {
+fn make_link(file_name: &str, link_name: &str) -> std::io::Result<()> {
     let mut src = std::env::current_dir().unwrap();
     let mut dst = src.clone();
     src.push("files");
     src.push(file_name);
     dst.push("links");
-    dst.push(file_name);
+    dst.push(link_name);
     std::os::unix::fs::symlink(src, dst)
 }
 
-fn do_add() {
-    prepare_environments();
-    match std::fs::read_dir("pending") {
-        Ok(entries) => {
-            for entry in entries.flatten() {
-                let file_name = entry.file_name().to_string_lossy().to_string();
-                do_add_file("pending", &file_name);
-            }
-        }
+/// Make symlinks of `(name, file_name)` entries into './links/', numbering them by their positions
+/// when a collection is given.
+fn make_links<'a>(entries: impl Iterator<Item = (&'a str, &'a str)>, collection: Option<&str>) {
+    match clear_directory("links") {
+        Ok(_) => {}
         Err(e) => {
-            eprintln!("failed to read directory: {e}");
+            eprintln!("failed to clear directory './links/': {e}");
+            return;
+        }
+    }
+    let positions = match collection {
+        Some(collection) => match query_collection_positions(collection) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        },
+        None => HashMap::new(),
+    };
+    let width = positions.len().to_string().len();
+    for (name, file_name) in entries {
+        let link_name = match positions.get(name) {
+            Some(position) => format!("{position:0width$}.{file_name}"),
+            None => file_name.to_string(),
+        };
+        match make_link(file_name, &link_name) {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("failed to make link for '{file_name}': {e}")
+            }
         }
     }
 }
 
-fn do_add_file(dir: &str, file_name: &str) {
-    let md = match std::fs::metadata(format!("{dir}/{file_name}")) {
-        Ok(md) => md,
+fn do_add(arg: AddArg) {
+    prepare_environments();
+    let mut files = vec![];
+    let mut folders = vec![];
+    if let Err(e) = scan_pending_files("pending", 1, &arg, &mut files, &mut folders) {
+        eprintln!("failed to read directory: {e}");
+        return;
+    }
+    let rules = match naming::load_rules() {
+        Ok(v) => v,
         Err(e) => {
-            eprintln!("failed to read file: {e}");
+            eprintln!("{e}");
             return;
         }
     };
-    if !md.is_file() {
-        println!("skip non-file '{file_name}'");
+    if arg.explain {
+        for (folder, file_name) in &files {
+            explain_name(&rules, folder, file_name);
+        }
         return;
     }
-    let file_size = md.len();
-    let base_name = file_name.split('.').next().unwrap();
-    let name: String = base_name
-        .chars()
-        .filter(|c| *c != '-')
-        .map(|c| c.to_ascii_uppercase())
-        .collect();
-    let result: Result<Option<u32>, _> = db_connection()
-        .query_row(
-            "SELECT 1 FROM video WHERE name=?",
-            rusqlite::params![&name],
-            |row| row.get(0),
-        )
-        .optional();
-    match result {
-        Ok(opt) => {
-            if opt.is_some() {
-                eprintln!(
-                    "skip file '{file_name}'; an entry with name '{name}' already existed in database"
-                );
+
+    // The folders and the tags of `entries` in order.
+    let mut sources: Vec<(&str, Vec<String>)> = vec![];
+    let mut entries: Vec<VideoEntry> = vec![];
+    for (folder, file_name) in &files {
+        let extracted = match naming::extract(&rules, file_name) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("skip file '{folder}/{file_name}'; {e}");
+                continue;
+            }
+        };
+        let entry = match read_video_file(folder, file_name, extracted.name) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let mut tags = extracted.tags;
+        if arg.folder_tags {
+            if let Some(path) = folder.strip_prefix("pending/") {
+                tags.push(path.to_ascii_uppercase());
+            }
+        }
+        if let Some(other) = entries.iter().find(|e| e.name == entry.name) {
+            eprintln!(
+                "skip file '{folder}/{file_name}'; an entry with name '{}' is added from file '{}'",
+                entry.name, other.file_name
+            );
+            continue;
+        }
+        sources.push((folder, tags));
+        entries.push(entry);
+    }
+
+    // Re-adding a video replaces its file, but keeps its ratings and plays.
+    let mut stmt = db_connection()
+        .prepare("SELECT name, file_name FROM video")
+        .unwrap();
+    let existing: HashMap<String, String> =
+        match stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))) {
+            Ok(rows) => match rows.collect() {
+                Ok(v) => v,
+                Err(e) => {
+                    eprintln!("failed to query database: {e}");
+                    return;
+                }
+            },
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
                 return;
             }
+        };
+    // The replaced files of `entries` in order.
+    let mut replaced: Vec<Option<&String>> = vec![];
+    for entry in &entries {
+        let existing_file = existing.get(&entry.name);
+        if let Some(file_name) = existing_file {
+            println!(
+                "replace file '{file_name}' of '{}' with '{}'",
+                entry.name, entry.file_name
+            );
+        }
+        replaced.push(existing_file.filter(|f| **f != entry.file_name));
+        print_video_file(entry);
+    }
+    if let Err(e) = VideoEntry::upsert_many(db_connection(), &entries) {
+        eprintln!("failed to add files: {e}");
+        return;
+    }
+    for ((entry, (folder, tags)), replaced) in entries.iter().zip(&sources).zip(&replaced) {
+        for tag in tags {
+            tag_added_video(&entry.name, tag);
+        }
+        if !move_into_files(folder, &entry.file_name) {
+            continue;
+        }
+        if let Some(file_name) = replaced {
+            match std::fs::remove_file(format!("files/{file_name}")) {
+                Ok(_) => {
+                    println!("replaced file '{file_name}' is removed");
+                }
+                Err(e) => {
+                    eprintln!("failed to remove replaced file '{file_name}': {e}");
+                }
+            }
+        }
+    }
+    // Only the emptied folders are removed, the deepest first.
+    for folder in folders.iter().rev() {
+        let _ = std::fs::remove_dir(folder);
+    }
+}
+
+/// Collect the files in `dir` as `(folder, file_name)`, scanning its folders recursively within
+/// the depth. The scanned folders are collected into `folders`, parents before children.
+fn scan_pending_files(
+    dir: &str,
+    depth: usize,
+    arg: &AddArg,
+    files: &mut Vec<(String, String)>,
+    folders: &mut Vec<String>,
+) -> std::io::Result<()> {
+    let mut dir_entries: Vec<_> = std::fs::read_dir(dir)?.flatten().collect();
+    dir_entries.sort_by_key(|e| e.file_name());
+    for dir_entry in dir_entries {
+        let file_name = dir_entry.file_name().to_string_lossy().to_string();
+        // Symbolic links are not followed, so that there are no cycles.
+        let is_dir = dir_entry.file_type().is_ok_and(|t| t.is_dir());
+        if !is_dir {
+            let included =
+                arg.include.is_empty() || arg.include.iter().any(|g| glob_matches(g, &file_name));
+            if !included || arg.exclude.iter().any(|g| glob_matches(g, &file_name)) {
+                println!("skip excluded file '{dir}/{file_name}'");
+                continue;
+            }
+            files.push((dir.to_string(), file_name));
+            continue;
         }
+        let path = format!("{dir}/{file_name}");
+        if let Some(limit) = arg.depth.filter(|limit| depth >= *limit) {
+            println!("skip folder '{path}'; it is deeper than {limit} level(s)");
+            continue;
+        }
+        folders.push(path.clone());
+        if let Err(e) = scan_pending_files(&path, depth + 1, arg, files, folders) {
+            eprintln!("skip folder '{path}'; failed to read directory: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Match the name against the glob with wildcards '*' and '?', ignoring case.
+fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut g, mut n) = (0, 0);
+    // The positions of the last '*' and the name it is matched up to, to backtrack to.
+    let mut star = None;
+    while n < name.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == name[n]) {
+            g += 1;
+            n += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, n));
+            g += 1;
+        } else if let Some((star_g, star_n)) = star {
+            g = star_g + 1;
+            n = star_n + 1;
+            star = Some((star_g, n));
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+fn explain_name(rules: &[naming::Rule], folder: &str, file_name: &str) {
+    let extracted = match naming::extract(rules, file_name) {
+        Ok(v) => v,
         Err(e) => {
-            eprintln!("failed to query database: {e}");
+            println!("'{folder}/{file_name}' is skipped; {e}");
             return;
         }
+    };
+    let name = &extracted.name;
+    match extracted.rule {
+        Some(rule) => {
+            let line = rule.line;
+            let regex = rule.regex.as_str();
+            println!("'{folder}/{file_name}' matches rule at line {line} '{regex}':");
+        }
+        None => {
+            println!(
+                "'{folder}/{file_name}' matches no rule, named by the part before the first '.':"
+            );
+        }
+    }
+    println!("  name={name}");
+    if let Some(part) = extracted.part {
+        println!("  part={part}");
+    }
+    if !extracted.tags.is_empty() {
+        println!("  tags={}", extracted.tags.join(","));
     }
+}
 
-    let mut entry = VideoEntry {
-        name,
-        file_name: file_name.to_string(),
-        file_size: file_size as _,
-        ..VideoEntry::default()
+fn tag_added_video(name: &str, tag: &str) {
+    let tag = match canonical_tag(db_connection(), tag) {
+        Ok(tag) => tag,
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
     };
-    match ffmpeg::format::input(&format!("{dir}/{file_name}")) {
-        Ok(input) => {
-            if input.duration() <= 0 {
-                eprintln!("skip file '{file_name}'; its duration is not a positive number");
-                return;
-            }
-            entry.duration = (input.duration() as f64 / ffmpeg::ffi::AV_TIME_BASE as f64) as _;
+    match add_video_tag(name, &tag) {
+        Ok(true) => {
+            println!("'{name}' is tagged with '{tag}'");
+        }
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("failed to tag '{name}' with '{tag}': {e}");
+        }
+    }
+}
+
+/// Read the metadata of the video file as entry `name`, printing the reason if it is skipped.
+fn read_video_file(dir: &str, file_name: &str, name: String) -> Option<VideoEntry> {
+    let md = match std::fs::metadata(format!("{dir}/{file_name}")) {
+        Ok(md) => md,
+        Err(e) => {
+            eprintln!("failed to read file: {e}");
+            return None;
+        }
+    };
+    if !md.is_file() {
+        println!("skip non-file '{file_name}'");
+        return None;
+    }
+    let file_size = md.len();
+
+    let mut entry = VideoEntry {
+        name,
+        file_name: file_name.to_string(),
+        file_size,
+        ..VideoEntry::default()
+    };
+    match ffmpeg::format::input(&format!("{dir}/{file_name}")) {
+        Ok(input) => {
+            if input.duration() <= 0 {
+                eprintln!("skip file '{file_name}'; its duration is not a positive number");
+                return None;
+            }
+            entry.duration = (input.duration() as f64 / ffmpeg::ffi::AV_TIME_BASE as f64) as _;
 
             let mut video_stream_read = false;
             let mut audio_stream_read = false;
@@ -416,56 +1550,1467 @@ fn do_add_file(dir: &str, file_name: &str) {
             }
         }
         Err(e) => {
-            eprintln!("skip file '{file_name}'; failed to read file: {e}");
+            eprintln!("skip file '{file_name}'; failed to read file: {e}");
+            return None;
+        }
+    }
+    Some(entry)
+}
+
+fn print_video_file(entry: &VideoEntry) {
+    println!("add file '{}' as '{}':", entry.file_name, entry.name);
+    println!("  file_size={}", readable_file_size(entry.file_size));
+    println!("  duration={}", readable_duration(entry.duration));
+    println!("  video_codec={}", entry.video_codec);
+    println!("  video_bit_rate={}kbps", entry.video_bit_rate);
+    println!(
+        "  video_frame_rate={}fps",
+        readable_frame_rate(entry.video_frame_rate)
+    );
+    println!("  video_width={}px", entry.video_width);
+    println!("  video_height={}px", entry.video_height);
+    println!("  audio_codec={}", entry.audio_codec);
+    println!("  audio_bit_rate={}kbps", entry.audio_bit_rate);
+}
+
+/// Return whether the file is moved.
+fn move_into_files(dir: &str, file_name: &str) -> bool {
+    match std::fs::rename(format!("{dir}/{file_name}"), format!("files/{file_name}")) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("failed to move file '{file_name}': {e}");
+            false
+        }
+    }
+}
+
+fn do_tag_add(name: String, tags: Vec<String>) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    match db_connection()
+        .query_row(
+            "SELECT 1 FROM video WHERE name=?",
+            rusqlite::params![&name],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            eprintln!("failed to tag '{name}', entry not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    for tag in tags {
+        let tag = match canonical_tag(db_connection(), &tag.to_ascii_uppercase()) {
+            Ok(tag) => tag,
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        };
+        match add_video_tag(&name, &tag) {
+            Ok(true) => {
+                println!("'{name}' is tagged with '{tag}'");
+            }
+            Ok(false) => {
+                println!("'{name}' is already tagged with '{tag}'");
+            }
+            Err(e) => {
+                eprintln!("failed to tag '{name}' with '{tag}': {e}");
+            }
+        }
+    }
+}
+
+fn add_video_tag(name: &str, tag: &str) -> rusqlite::Result<bool> {
+    let c = db_connection();
+    let existed = c
+        .query_row(
+            "SELECT 1 FROM video_tag WHERE video=? AND tag=?",
+            rusqlite::params![name, tag],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()?
+        .is_some();
+    if existed {
+        return Ok(false);
+    }
+    insert_tag(c, tag)?;
+    let entry = VideoTagEntry {
+        video: name.to_string(),
+        tag: tag.to_string(),
+    };
+    entry.insert(c)?;
+    Ok(true)
+}
+
+fn canonical_tag(c: &rusqlite::Connection, tag: &str) -> rusqlite::Result<String> {
+    let canonical = c
+        .query_row(
+            "SELECT tag FROM tag_alias WHERE alias=?",
+            rusqlite::params![tag],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(canonical.unwrap_or_else(|| tag.to_string()))
+}
+
+fn insert_tag(c: &rusqlite::Connection, tag: &str) -> rusqlite::Result<()> {
+    if tag_exists(c, tag)? {
+        return Ok(());
+    }
+    let entry = TagEntry {
+        name: tag.to_string(),
+        namespace: tag_namespace(tag).to_string(),
+    };
+    entry.insert(c)?;
+    if let Some((parent, _)) = tag.rsplit_once('/') {
+        insert_tag(c, parent)?;
+        c.execute(
+            "INSERT OR IGNORE INTO tag_parent(tag, parent) VALUES (?, ?)",
+            rusqlite::params![tag, parent],
+        )?;
+    }
+    Ok(())
+}
+
+fn do_tag_remove(name: String, tags: Vec<String>) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    for tag in tags {
+        let tag = match canonical_tag(db_connection(), &tag.to_ascii_uppercase()) {
+            Ok(tag) => tag,
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        };
+        match VideoTagEntry::delete_where(
+            db_connection(),
+            "WHERE video=? AND tag=?",
+            rusqlite::params![&name, &tag],
+        ) {
+            Ok(n) => {
+                if n > 0 {
+                    println!("tag '{tag}' is removed from '{name}'");
+                } else {
+                    eprintln!("'{name}' is not tagged with '{tag}'");
+                }
+            }
+            Err(e) => {
+                eprintln!("failed to remove tag '{tag}' from '{name}': {e}");
+            }
+        }
+    }
+}
+
+fn do_tag_list(name: Option<String>) {
+    prepare_environments();
+    let tags = match &name {
+        Some(name) => {
+            let name = name.to_ascii_uppercase();
+            VideoTagEntry::select_by_video(db_connection(), &name).map(|entries| {
+                let mut tags: Vec<_> = entries.into_iter().map(|e| e.tag).collect();
+                tags.sort();
+                tags
+            })
+        }
+        None => TagEntry::select(db_connection(), "ORDER BY namespace, name", [])
+            .map(|entries| entries.into_iter().map(|e| e.name).collect::<Vec<_>>()),
+    };
+    match tags {
+        Ok(tags) => {
+            for tag in tags {
+                println!("{tag}");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+        }
+    }
+}
+
+fn do_tags_list() {
+    prepare_environments();
+    let mut stmt = db_connection()
+        .prepare(
+            "SELECT tag.name, COUNT(video.name), COALESCE(SUM(video.duration), 0) FROM tag \
+            LEFT JOIN video_tag ON video_tag.tag=tag.name \
+            LEFT JOIN video ON video.name=video_tag.video \
+            GROUP BY tag.name ORDER BY tag.namespace, tag.name",
+        )
+        .unwrap();
+    let rows = match stmt.query_map([], |row| {
+        let tag: String = row.get(0)?;
+        let count: u32 = row.get(1)?;
+        let duration: u64 = row.get(2)?;
+        Ok((tag, count, duration))
+    }) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    for row in rows {
+        match row {
+            Ok((tag, count, duration)) => {
+                let duration = readable_duration(duration);
+                println!("{tag} videos={count} duration={duration}");
+            }
+            Err(e) => {
+                eprintln!("failed to query database: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+fn do_tags_rename(from: String, to: String) {
+    prepare_environments();
+    let from = from.to_ascii_uppercase();
+    let to = to.to_ascii_uppercase();
+    match tag_exists(db_connection(), &from) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("failed to rename tag '{from}', not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    if to.starts_with(&format!("{from}/")) {
+        eprintln!("failed to rename tag '{from}'; '{to}' is named under it");
+        return;
+    }
+    match tag_exists(db_connection(), &to) {
+        Ok(false) => {}
+        Ok(true) => {
+            eprintln!("failed to rename tag '{from}'; tag '{to}' already existed, use subcommand 'tags merge' instead");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    match merge_tags(std::slice::from_ref(&from), &to) {
+        Ok(counts) => {
+            let n = counts[0];
+            println!("tag '{from}' is renamed to '{to}' on {n} video(s)");
+        }
+        Err(e) => {
+            eprintln!("failed to rename tag '{from}': {e}");
+        }
+    }
+}
+
+fn do_tags_merge(tags: Vec<String>, into: String) {
+    prepare_environments();
+    let into = into.to_ascii_uppercase();
+    let mut merged = vec![];
+    for tag in tags {
+        let tag = tag.to_ascii_uppercase();
+        if tag == into || merged.contains(&tag) {
+            continue;
+        }
+        if into.starts_with(&format!("{tag}/")) {
+            eprintln!("skip tag '{tag}'; '{into}' is named under it");
+            continue;
+        }
+        match tag_exists(db_connection(), &tag) {
+            Ok(true) => merged.push(tag),
+            Ok(false) => {
+                eprintln!("skip tag '{tag}', not found");
+            }
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        }
+    }
+    match merge_tags(&merged, &into) {
+        Ok(counts) => {
+            for (tag, n) in merged.iter().zip(counts) {
+                println!("tag '{tag}' is merged into '{into}' on {n} video(s)");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to merge tags into '{into}': {e}");
+        }
+    }
+}
+
+fn do_tags_delete(tag: String) {
+    prepare_environments();
+    let tag = tag.to_ascii_uppercase();
+    match tag_exists(db_connection(), &tag) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("failed to delete tag '{tag}', not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    match delete_tag(&tag) {
+        Ok(n) => {
+            println!("tag '{tag}' is deleted from {n} video(s)");
+        }
+        Err(e) => {
+            eprintln!("failed to delete tag '{tag}': {e}");
+        }
+    }
+}
+
+fn tag_exists(c: &rusqlite::Connection, tag: &str) -> rusqlite::Result<bool> {
+    let existed = c
+        .query_row(
+            "SELECT 1 FROM tag WHERE name=?",
+            rusqlite::params![tag],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()?
+        .is_some();
+    Ok(existed)
+}
+
+/// Move every video tagged by one of `tags` over to `into` and drop those tags, along with the
+/// tags named under them. Return the number of videos which carried each tag.
+fn merge_tags(tags: &[String], into: &str) -> rusqlite::Result<Vec<usize>> {
+    let tx = db_connection().unchecked_transaction()?;
+    let mut counts = vec![];
+    for tag in tags {
+        counts.push(merge_tag(&tx, tag, into)?);
+    }
+    tx.commit()?;
+    Ok(counts)
+}
+
+fn merge_tag(c: &rusqlite::Connection, from: &str, into: &str) -> rusqlite::Result<usize> {
+    insert_tag(c, into)?;
+    c.execute(
+        "INSERT INTO video_tag(video, tag) SELECT video, ?1 FROM video_tag \
+        WHERE tag=?2 AND video NOT IN (SELECT video FROM video_tag WHERE tag=?1)",
+        rusqlite::params![into, from],
+    )?;
+    let n = VideoTagEntry::delete_where(c, "WHERE tag=?", rusqlite::params![from])?;
+    c.execute(
+        "UPDATE tag_alias SET tag=? WHERE tag=?",
+        rusqlite::params![into, from],
+    )?;
+    TagParentEntry::delete_where(c, "WHERE tag=? AND parent=?", rusqlite::params![into, from])?;
+    c.execute(
+        "UPDATE tag_parent SET parent=? WHERE parent=?",
+        rusqlite::params![into, from],
+    )?;
+    // The children named under `from` are renamed under `into` to keep matching their parent.
+    let prefix = format!("{from}/");
+    let children = TagParentEntry::select(c, "WHERE parent=?", rusqlite::params![into])?;
+    for child in children {
+        if let Some(rest) = child.tag.strip_prefix(&prefix) {
+            merge_tag(c, &child.tag, &format!("{into}/{rest}"))?;
+        }
+    }
+    // Moved the aliases and children first, since the rest are deleted in cascade.
+    TagEntry::delete_where(c, "WHERE name=?", rusqlite::params![from])?;
+    Ok(n)
+}
+
+fn delete_tag(tag: &str) -> rusqlite::Result<usize> {
+    let tx = db_connection().unchecked_transaction()?;
+    let n = VideoTagEntry::delete_where(&tx, "WHERE tag=?", rusqlite::params![tag])?;
+    // Its aliases and parents are deleted in cascade.
+    TagEntry::delete_where(&tx, "WHERE name=?", rusqlite::params![tag])?;
+    tx.commit()?;
+    Ok(n)
+}
+
+fn do_tags_tree() {
+    prepare_environments();
+    let tags = TagEntry::select(db_connection(), "ORDER BY namespace, name", []);
+    let parents = TagParentEntry::select(db_connection(), "ORDER BY tag", []);
+    let video_tags = VideoTagEntry::select(db_connection(), "", []);
+    let (tags, parents, video_tags) = match (tags, parents, video_tags) {
+        (Ok(t), Ok(p), Ok(v)) => (t, p, v),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    };
+
+    let mut tree = TagTree::default();
+    let mut has_parent = HashSet::new();
+    for entry in &parents {
+        tree.children
+            .entry(&entry.parent)
+            .or_default()
+            .push(&entry.tag);
+        has_parent.insert(entry.tag.as_str());
+    }
+    for entry in &video_tags {
+        tree.videos
+            .entry(&entry.tag)
+            .or_default()
+            .push(&entry.video);
+    }
+    for tag in &tags {
+        if !has_parent.contains(tag.name.as_str()) {
+            tree.print(&tag.name, &mut vec![]);
+        }
+    }
+}
+
+#[derive(Default)]
+struct TagTree<'a> {
+    children: HashMap<&'a str, Vec<&'a str>>,
+    videos: HashMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> TagTree<'a> {
+    fn collect_videos(&self, tag: &'a str, videos: &mut HashSet<&'a str>, path: &mut Vec<&'a str>) {
+        if path.contains(&tag) {
+            return;
+        }
+        if let Some(v) = self.videos.get(tag) {
+            videos.extend(v);
+        }
+        path.push(tag);
+        for child in self.children.get(tag).into_iter().flatten() {
+            self.collect_videos(child, videos, path);
+        }
+        path.pop();
+    }
+
+    fn print(&self, tag: &'a str, path: &mut Vec<&'a str>) {
+        if path.contains(&tag) {
+            return;
+        }
+        let mut videos = HashSet::new();
+        self.collect_videos(tag, &mut videos, &mut path.clone());
+        let indent = "  ".repeat(path.len());
+        let count = videos.len();
+        println!("{indent}{tag} videos={count}");
+        path.push(tag);
+        for child in self.children.get(tag).into_iter().flatten() {
+            self.print(child, path);
+        }
+        path.pop();
+    }
+}
+
+fn do_tags_parent(tag: String, parent: Option<String>) {
+    prepare_environments();
+    let tag = tag.to_ascii_uppercase();
+    match tag_exists(db_connection(), &tag) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("failed to set the parent of tag '{tag}', not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    if let Some((named_parent, _)) = tag.rsplit_once('/') {
+        eprintln!("failed to set the parent of tag '{tag}'; it is named under '{named_parent}', use subcommand 'tags rename' instead");
+        return;
+    }
+    let parent = match parent {
+        Some(parent) => parent.to_ascii_uppercase(),
+        None => {
+            match TagParentEntry::delete_where(
+                db_connection(),
+                "WHERE tag=?",
+                rusqlite::params![&tag],
+            ) {
+                Ok(_) => {
+                    println!("tag '{tag}' has no parent now");
+                }
+                Err(e) => {
+                    eprintln!("failed to unset the parent of tag '{tag}': {e}");
+                }
+            }
+            return;
+        }
+    };
+    let is_descendant: rusqlite::Result<Option<u32>> = db_connection()
+        .query_row(
+            "WITH RECURSIVE ancestor(name) AS (\
+            SELECT ?1 UNION SELECT tag_parent.parent FROM tag_parent \
+            JOIN ancestor ON tag_parent.tag=ancestor.name) \
+            SELECT 1 FROM ancestor WHERE name=?2",
+            rusqlite::params![&parent, &tag],
+            |row| row.get(0),
+        )
+        .optional();
+    match is_descendant {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            eprintln!("failed to set the parent of tag '{tag}'; '{parent}' is a descendant of it");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    let result = insert_tag(db_connection(), &parent).and_then(|_| {
+        db_connection().execute(
+            "INSERT OR REPLACE INTO tag_parent(tag, parent) VALUES (?, ?)",
+            rusqlite::params![&tag, &parent],
+        )
+    });
+    match result {
+        Ok(_) => {
+            println!("tag '{tag}' is a child of '{parent}' now");
+        }
+        Err(e) => {
+            eprintln!("failed to set the parent of tag '{tag}': {e}");
+        }
+    }
+}
+
+fn do_alias_add(tag: String, aliases: Vec<String>) {
+    prepare_environments();
+    let tag = tag.to_ascii_uppercase();
+    match canonical_tag(db_connection(), &tag) {
+        Ok(canonical) if canonical != tag => {
+            eprintln!("failed to add aliases; '{tag}' is already an alias of '{canonical}'");
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    let mut added = vec![];
+    for alias in aliases {
+        let alias = alias.to_ascii_uppercase();
+        if alias == tag || added.contains(&alias) {
+            continue;
+        }
+        match canonical_tag(db_connection(), &alias) {
+            Ok(canonical) if canonical != alias => {
+                eprintln!("skip '{alias}'; it is already an alias of '{canonical}'");
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        }
+        match TagAliasEntry::select(
+            db_connection(),
+            "WHERE tag=? LIMIT 1",
+            rusqlite::params![&alias],
+        ) {
+            Ok(v) if !v.is_empty() => {
+                eprintln!("skip '{alias}'; it is a tag with aliases itself");
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        }
+        added.push(alias);
+    }
+    if added.is_empty() {
+        return;
+    }
+    match add_tag_aliases(&tag, &added) {
+        Ok(counts) => {
+            for (alias, n) in added.iter().zip(counts) {
+                println!("'{alias}' is an alias of '{tag}' now; {n} video(s) retagged");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to add aliases of '{tag}': {e}");
+        }
+    }
+}
+
+/// Record `aliases` as aliases of `tag`, retagging the videos carrying any of them.
+/// Return the number of retagged videos for each alias.
+fn add_tag_aliases(tag: &str, aliases: &[String]) -> rusqlite::Result<Vec<usize>> {
+    let tx = db_connection().unchecked_transaction()?;
+    insert_tag(&tx, tag)?;
+    let mut counts = vec![];
+    for alias in aliases {
+        counts.push(merge_tag(&tx, alias, tag)?);
+        let entry = TagAliasEntry {
+            alias: alias.clone(),
+            tag: tag.to_string(),
+        };
+        entry.insert(&tx)?;
+    }
+    tx.commit()?;
+    Ok(counts)
+}
+
+fn do_alias_remove(aliases: Vec<String>) {
+    prepare_environments();
+    for alias in aliases {
+        let alias = alias.to_ascii_uppercase();
+        match TagAliasEntry::delete_where(
+            db_connection(),
+            "WHERE alias=?",
+            rusqlite::params![&alias],
+        ) {
+            Ok(n) => {
+                if n > 0 {
+                    println!("alias '{alias}' is removed");
+                } else {
+                    eprintln!("alias '{alias}' not found");
+                }
+            }
+            Err(e) => {
+                eprintln!("failed to remove alias '{alias}': {e}");
+            }
+        }
+    }
+}
+
+fn do_alias_list(tag: Option<String>) {
+    prepare_environments();
+    let entries = match &tag {
+        Some(tag) => {
+            let tag = tag.to_ascii_uppercase();
+            TagAliasEntry::select_by_tag(db_connection(), &tag).map(|mut entries| {
+                entries.sort_by(|a, b| a.alias.cmp(&b.alias));
+                entries
+            })
+        }
+        None => TagAliasEntry::select(db_connection(), "ORDER BY tag, alias", []),
+    };
+    let entries = match entries {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    };
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for entry in &entries {
+        groups.entry(&entry.tag).or_default().push(&entry.alias);
+    }
+    for (tag, aliases) in groups {
+        let aliases = aliases.join(",");
+        println!("{tag}: {aliases}");
+    }
+}
+
+fn do_rate(name: String, rating: u32) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    let entry = VideoEntry {
+        name,
+        rating,
+        ..Default::default()
+    };
+    let name = &entry.name;
+    match entry.update_fields(db_connection(), &[VideoEntryField::Rating]) {
+        Ok(n) => {
+            if n > 0 {
+                println!("'{name}' is rated {rating}");
+            } else {
+                eprintln!("failed to rate '{name}', entry not found");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to rate '{name}': {e}");
+        }
+    }
+}
+
+fn do_fav(name: String, favorite: bool) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    let entry = VideoEntry {
+        name,
+        favorite,
+        ..Default::default()
+    };
+    let name = &entry.name;
+    match entry.update_fields(db_connection(), &[VideoEntryField::Favorite]) {
+        Ok(n) => {
+            if n == 0 {
+                eprintln!("failed to mark '{name}', entry not found");
+            } else if favorite {
+                println!("'{name}' is marked as favorite");
+            } else {
+                println!("'{name}' is unmarked as favorite");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to mark '{name}': {e}");
+        }
+    }
+}
+
+fn do_collection_create(name: String) {
+    prepare_environments();
+    match db_connection().execute(
+        "INSERT OR IGNORE INTO collection(name) VALUES (?)",
+        rusqlite::params![&name],
+    ) {
+        Ok(n) => {
+            if n > 0 {
+                println!("collection '{name}' is created");
+            } else {
+                eprintln!("collection '{name}' already existed");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to create collection '{name}': {e}");
+        }
+    }
+}
+
+fn collection_exists(collection: &str) -> rusqlite::Result<bool> {
+    let existed = db_connection()
+        .query_row(
+            "SELECT 1 FROM collection WHERE name=?",
+            rusqlite::params![collection],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()?
+        .is_some();
+    Ok(existed)
+}
+
+fn do_collection_add(collection: String, name: String, position: Option<u32>) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    match collection_exists(&collection) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("collection '{collection}' not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    match db_connection()
+        .query_row(
+            "SELECT 1 FROM video WHERE name=?",
+            rusqlite::params![&name],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            eprintln!("entry '{name}' not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    match add_collection_video(&collection, &name, position) {
+        Ok(position) => {
+            println!("'{name}' is added into collection '{collection}' at position {position}");
+        }
+        Err(e) => {
+            eprintln!("failed to add '{name}' into collection '{collection}': {e}");
+        }
+    }
+}
+
+/// Insert the video at `position` of the collection, shifting the following videos backward.
+/// Return the actual position, which is clamped into the collection.
+fn add_collection_video(
+    collection: &str,
+    name: &str,
+    position: Option<u32>,
+) -> rusqlite::Result<u32> {
+    let tx = db_connection().unchecked_transaction()?;
+    remove_collection_video(&tx, collection, name)?;
+    let last: u32 = tx.query_row(
+        "SELECT COALESCE(MAX(position), 0) FROM collection_video WHERE collection=?",
+        rusqlite::params![collection],
+        |row| row.get(0),
+    )?;
+    let position = position.unwrap_or(last + 1).clamp(1, last + 1);
+    tx.execute(
+        "UPDATE collection_video SET position=position+1 WHERE collection=? AND position>=?",
+        rusqlite::params![collection, position],
+    )?;
+    let entry = CollectionVideoEntry {
+        collection: collection.to_string(),
+        video: name.to_string(),
+        position,
+    };
+    entry.insert(&tx)?;
+    tx.commit()?;
+    Ok(position)
+}
+
+/// Remove the video from the collection, shifting the following videos forward.
+fn remove_collection_video(
+    c: &rusqlite::Connection,
+    collection: &str,
+    name: &str,
+) -> rusqlite::Result<bool> {
+    let position: Option<u32> = c
+        .query_row(
+            "SELECT position FROM collection_video WHERE collection=? AND video=?",
+            rusqlite::params![collection, name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let position = match position {
+        Some(p) => p,
+        None => return Ok(false),
+    };
+    CollectionVideoEntry::delete_where(
+        c,
+        "WHERE collection=? AND video=?",
+        rusqlite::params![collection, name],
+    )?;
+    c.execute(
+        "UPDATE collection_video SET position=position-1 WHERE collection=? AND position>?",
+        rusqlite::params![collection, position],
+    )?;
+    Ok(true)
+}
+
+fn do_collection_remove(collection: String, name: String) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    let result = db_connection().unchecked_transaction().and_then(|tx| {
+        let removed = remove_collection_video(&tx, &collection, &name)?;
+        tx.commit()?;
+        Ok(removed)
+    });
+    match result {
+        Ok(true) => {
+            println!("'{name}' is removed from collection '{collection}'");
+        }
+        Ok(false) => {
+            eprintln!("'{name}' is not in collection '{collection}'");
+        }
+        Err(e) => {
+            eprintln!("failed to remove '{name}' from collection '{collection}': {e}");
+        }
+    }
+}
+
+fn do_collection_show(collection: Option<String>) {
+    prepare_environments();
+    let collection = match collection {
+        Some(c) => c,
+        None => {
+            match CollectionEntry::select(db_connection(), "ORDER BY name", []) {
+                Ok(entries) => {
+                    for entry in entries {
+                        println!("{}", entry.name);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("failed to query database: {e}");
+                }
+            }
+            return;
+        }
+    };
+    match collection_exists(&collection) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("collection '{collection}' not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    match CollectionVideoEntry::select(
+        db_connection(),
+        "WHERE collection=? ORDER BY position",
+        rusqlite::params![&collection],
+    ) {
+        Ok(entries) => {
+            for entry in entries {
+                let position = entry.position;
+                let video = &entry.video;
+                println!("{position}. {video}");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+        }
+    }
+}
+
+fn query_collection_positions(collection: &str) -> rusqlite::Result<HashMap<String, u32>> {
+    let entries = CollectionVideoEntry::select_by_collection(db_connection(), collection)?;
+    Ok(entries.into_iter().map(|e| (e.video, e.position)).collect())
+}
+
+fn resolve_person(c: &rusqlite::Connection, name: &str) -> rusqlite::Result<Option<String>> {
+    c.query_row(
+        "SELECT name FROM person WHERE name=IFNULL((SELECT person FROM person_alias WHERE alias=?1), ?1)",
+        rusqlite::params![name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Resolve the name or alias of the person, printing the error if it is not found.
+fn find_person(name: &str) -> Option<String> {
+    let name = name.to_ascii_uppercase();
+    match resolve_person(db_connection(), &name) {
+        Ok(Some(person)) => Some(person),
+        Ok(None) => {
+            eprintln!("person '{name}' not found");
+            None
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            None
+        }
+    }
+}
+
+fn do_person_add(name: String, aliases: Vec<String>) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    match resolve_person(db_connection(), &name) {
+        Ok(Some(person)) if person == name => {
+            eprintln!("person '{name}' already existed");
+            return;
+        }
+        Ok(Some(person)) => {
+            eprintln!("failed to create person; '{name}' is already an alias of '{person}'");
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    }
+    let entry = PersonEntry {
+        name: name.clone(),
+        ..Default::default()
+    };
+    match entry.insert(db_connection()) {
+        Ok(_) => {
+            println!("person '{name}' is created");
+        }
+        Err(e) => {
+            eprintln!("failed to create person '{name}': {e}");
+            return;
+        }
+    }
+    add_person_aliases(&name, aliases);
+}
+
+fn do_person_remove(name: String) {
+    prepare_environments();
+    let person = match find_person(&name) {
+        Some(p) => p,
+        None => return,
+    };
+    let result = db_connection().unchecked_transaction().and_then(|tx| {
+        let mut entries = PersonEntry::select(&tx, "WHERE name=?", rusqlite::params![&person])?;
+        let entry = entries.remove(0);
+        let params = rusqlite::params![&entry.name];
+        let n = VideoPersonEntry::delete_where(&tx, "WHERE person=?", params)?;
+        // Its aliases are deleted in cascade.
+        entry.delete(&tx)?;
+        tx.commit()?;
+        Ok((entry.portrait, n))
+    });
+    match result {
+        Ok((portrait, n)) => {
+            if !portrait.is_empty() {
+                let _ = std::fs::remove_file(format!("portraits/{portrait}"));
+            }
+            println!("person '{person}' is removed; {n} video(s) unlinked");
+        }
+        Err(e) => {
+            eprintln!("failed to remove person '{person}': {e}");
+        }
+    }
+}
+
+fn do_person_alias(name: String, aliases: Vec<String>, remove: bool) {
+    prepare_environments();
+    let person = match find_person(&name) {
+        Some(p) => p,
+        None => return,
+    };
+    if !remove {
+        add_person_aliases(&person, aliases);
+        return;
+    }
+    for alias in aliases {
+        let alias = alias.to_ascii_uppercase();
+        match PersonAliasEntry::delete_where(
+            db_connection(),
+            "WHERE alias=? AND person=?",
+            rusqlite::params![&alias, &person],
+        ) {
+            Ok(n) if n > 0 => {
+                println!("'{alias}' is not an alias of '{person}' any more");
+            }
+            Ok(_) => {
+                eprintln!("'{alias}' is not an alias of '{person}'");
+            }
+            Err(e) => {
+                eprintln!("failed to remove alias '{alias}': {e}");
+            }
+        }
+    }
+}
+
+fn add_person_aliases(person: &str, aliases: Vec<String>) {
+    for alias in aliases {
+        let alias = alias.to_ascii_uppercase();
+        match resolve_person(db_connection(), &alias) {
+            Ok(Some(existed)) => {
+                eprintln!("skip '{alias}'; it already refers to person '{existed}'");
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        }
+        let entry = PersonAliasEntry {
+            alias: alias.clone(),
+            person: person.to_string(),
+        };
+        match entry.insert(db_connection()) {
+            Ok(_) => {
+                println!("'{alias}' is an alias of '{person}' now");
+            }
+            Err(e) => {
+                eprintln!("failed to add alias '{alias}': {e}");
+            }
+        }
+    }
+}
+
+fn do_person_note(name: String) {
+    prepare_environments();
+    let person = match find_person(&name) {
+        Some(p) => p,
+        None => return,
+    };
+    let note: String = match db_connection().query_row(
+        "SELECT note FROM person WHERE name=?",
+        rusqlite::params![&person],
+        |row| row.get(0),
+    ) {
+        Ok(note) => note,
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    };
+
+    let path = std::env::temp_dir().join(format!("ddvm-person-{person}.txt"));
+    match std::fs::write(&path, &note) {
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("failed to write temporary file: {e}");
+            return;
+        }
+    }
+    let result = edit_file(&path).and_then(|_| std::fs::read_to_string(&path));
+    let _ = std::fs::remove_file(&path);
+    let note = match result {
+        Ok(s) => s.trim_end().to_string(),
+        Err(e) => {
+            eprintln!("failed to edit the notes of '{person}': {e}");
+            return;
+        }
+    };
+
+    let entry = PersonEntry {
+        name: person.clone(),
+        note,
+        ..Default::default()
+    };
+    match entry.update_fields(db_connection(), &[PersonEntryField::Note]) {
+        Ok(_) => {
+            println!("the notes of '{person}' are saved");
+        }
+        Err(e) => {
+            eprintln!("failed to save the notes of '{person}': {e}");
+        }
+    }
+}
+
+fn do_person_portrait(name: String, file: Option<String>) {
+    prepare_environments();
+    let person = match find_person(&name) {
+        Some(p) => p,
+        None => return,
+    };
+    let old: String = match db_connection().query_row(
+        "SELECT portrait FROM person WHERE name=?",
+        rusqlite::params![&person],
+        |row| row.get(0),
+    ) {
+        Ok(portrait) => portrait,
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    };
+    let portrait = match &file {
+        Some(file) => {
+            let path = std::path::Path::new(file);
+            let portrait = match path.extension() {
+                Some(ext) => format!("{person}.{}", ext.to_string_lossy()),
+                None => person.clone(),
+            };
+            match std::fs::copy(path, format!("portraits/{portrait}")) {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("failed to copy '{file}': {e}");
+                    return;
+                }
+            }
+            portrait
+        }
+        None => String::new(),
+    };
+    if !old.is_empty() && old != portrait {
+        let _ = std::fs::remove_file(format!("portraits/{old}"));
+    }
+    let entry = PersonEntry {
+        name: person.clone(),
+        portrait,
+        ..Default::default()
+    };
+    let portrait = &entry.portrait;
+    match entry.update_fields(db_connection(), &[PersonEntryField::Portrait]) {
+        Ok(_) => {
+            if portrait.is_empty() {
+                println!("the portrait of '{person}' is cleared");
+            } else {
+                println!("the portrait of '{person}' is saved as './portraits/{portrait}'");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to save the portrait of '{person}': {e}");
+        }
+    }
+}
+
+fn do_person_link(person: String, names: Vec<String>) {
+    prepare_environments();
+    let person = match find_person(&person) {
+        Some(p) => p,
+        None => return,
+    };
+    for name in names {
+        let name = name.to_ascii_uppercase();
+        match link_video_person(&name, &person) {
+            Ok(Some(true)) => {
+                println!("'{name}' is linked to '{person}'");
+            }
+            Ok(Some(false)) => {
+                println!("'{name}' is already linked to '{person}'");
+            }
+            Ok(None) => {
+                eprintln!("failed to link '{name}', entry not found");
+            }
+            Err(e) => {
+                eprintln!("failed to link '{name}' to '{person}': {e}");
+            }
+        }
+    }
+}
+
+/// Return `None` if the video does not exist, otherwise whether the link is newly made.
+fn link_video_person(name: &str, person: &str) -> rusqlite::Result<Option<bool>> {
+    let c = db_connection();
+    let existed = c
+        .query_row(
+            "SELECT 1 FROM video WHERE name=?",
+            rusqlite::params![name],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()?;
+    if existed.is_none() {
+        return Ok(None);
+    }
+    let entries = VideoPersonEntry::select(
+        c,
+        "WHERE video=? AND person=?",
+        rusqlite::params![name, person],
+    )?;
+    if !entries.is_empty() {
+        return Ok(Some(false));
+    }
+    let entry = VideoPersonEntry {
+        video: name.to_string(),
+        person: person.to_string(),
+    };
+    entry.insert(c)?;
+    Ok(Some(true))
+}
+
+fn do_person_unlink(person: String, names: Vec<String>) {
+    prepare_environments();
+    let person = match find_person(&person) {
+        Some(p) => p,
+        None => return,
+    };
+    for name in names {
+        let name = name.to_ascii_uppercase();
+        match VideoPersonEntry::delete_where(
+            db_connection(),
+            "WHERE video=? AND person=?",
+            rusqlite::params![&name, &person],
+        ) {
+            Ok(n) if n > 0 => {
+                println!("'{name}' is unlinked from '{person}'");
+            }
+            Ok(_) => {
+                eprintln!("'{name}' is not linked to '{person}'");
+            }
+            Err(e) => {
+                eprintln!("failed to unlink '{name}' from '{person}': {e}");
+            }
+        }
+    }
+}
+
+fn do_person_list() {
+    prepare_environments();
+    let aliases = match query_person_aliases() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    let mut stmt = db_connection()
+        .prepare(
+            "SELECT person.name, COUNT(video_person.video) FROM person \
+            LEFT JOIN video_person ON video_person.person=person.name \
+            GROUP BY person.name ORDER BY person.name",
+        )
+        .unwrap();
+    let rows = match stmt.query_map([], |row| {
+        let person: String = row.get(0)?;
+        let count: u32 = row.get(1)?;
+        Ok((person, count))
+    }) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    for row in rows {
+        match row {
+            Ok((person, count)) => match aliases.get(&person) {
+                Some(aliases) => {
+                    let aliases = aliases.join(",");
+                    println!("{person}({aliases}) videos={count}");
+                }
+                None => {
+                    println!("{person} videos={count}");
+                }
+            },
+            Err(e) => {
+                eprintln!("failed to query database: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+fn do_person_show(name: String) {
+    prepare_environments();
+    let person = match find_person(&name) {
+        Some(p) => p,
+        None => return,
+    };
+    let entry =
+        match PersonEntry::select(db_connection(), "WHERE name=?", rusqlite::params![&person]) {
+            Ok(mut v) => v.remove(0),
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
+        };
+    let aliases = match query_person_aliases() {
+        Ok(mut v) => v.remove(&person).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    };
+    let videos = match BriefVideoEntry::select(
+        db_connection(),
+        "WHERE name IN (SELECT video FROM video_person WHERE person=?) ORDER BY name",
+        rusqlite::params![&person],
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
             return;
         }
-    }
-
-    println!("add file '{file_name}' as '{}':", entry.name);
-    println!("  file_size={}", readable_file_size(entry.file_size));
-    println!("  duration={}", readable_duration(entry.duration));
-    println!("  video_codec={}", entry.video_codec);
-    println!("  video_bit_rate={}kbps", entry.video_bit_rate);
-    println!("  video_frame_rate={}fps", entry.video_frame_rate);
-    println!("  video_width={}px", entry.video_width);
-    println!("  video_height={}px", entry.video_height);
-    println!("  audio_codec={}", entry.audio_codec);
-    println!("  audio_bit_rate={}kbps", entry.audio_bit_rate);
-    match entry.insert(db_connection()) {
-        Ok(_) => {}
+    };
+    let co_tags = match query_person_co_tags(&person, 5) {
+        Ok(v) => v,
         Err(e) => {
-            eprintln!("skip file '{file_name}'; failed to insert entry: {e}");
+            eprintln!("failed to query database: {e}");
             return;
         }
+    };
+
+    println!("{person}");
+    if !aliases.is_empty() {
+        let aliases = aliases.join(", ");
+        println!("  aliases: {aliases}");
+    }
+    if !entry.portrait.is_empty() {
+        let portrait = &entry.portrait;
+        println!("  portrait: ./portraits/{portrait}");
     }
+    let count = videos.len();
+    let duration = readable_duration(videos.iter().map(|e| e.duration).sum());
+    println!("  videos: count={count}, duration={duration}");
+    if !co_tags.is_empty() {
+        let co_tags: Vec<String> = co_tags
+            .iter()
+            .map(|(tag, n)| format!("{tag}({n})"))
+            .collect();
+        let co_tags = co_tags.join(", ");
+        println!("  common tags: {co_tags}");
+    }
+    if let Some(note) = entry.note.lines().next() {
+        println!("  note: {note}");
+    }
+    for video in &videos {
+        let name = &video.name;
+        let duration = readable_duration(video.duration);
+        let rating = readable_rating(video.rating, video.favorite);
+        println!("  - {name} {duration}{rating}");
+    }
+}
 
-    if dir != "files" {
-        match std::fs::rename(format!("{dir}/{file_name}"), format!("files/{file_name}")) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("failed to move file '{file_name}': {e}");
-            }
-        }
+fn query_person_aliases() -> rusqlite::Result<HashMap<String, Vec<String>>> {
+    let entries = PersonAliasEntry::select(db_connection(), "ORDER BY person, alias", [])?;
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in entries {
+        aliases.entry(entry.person).or_default().push(entry.alias);
     }
+    Ok(aliases)
 }
 
-fn do_tag(name: String, tag: String) {
+/// Return the most common tags among the videos of the person, with their numbers of videos.
+fn query_person_co_tags(person: &str, limit: usize) -> rusqlite::Result<Vec<(String, u32)>> {
+    let mut stmt = db_connection().prepare(
+        "SELECT tag, COUNT(*) AS n FROM video_tag \
+        WHERE video IN (SELECT video FROM video_person WHERE person=?) \
+        GROUP BY tag ORDER BY n DESC, tag LIMIT ?",
+    )?;
+    let rows = stmt.query_map(rusqlite::params![person, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    rows.collect()
+}
+
+fn query_video_persons() -> rusqlite::Result<HashMap<String, Vec<String>>> {
+    let entries = VideoPersonEntry::select(db_connection(), "ORDER BY video, person", [])?;
+    let mut persons: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in entries {
+        persons.entry(entry.video).or_default().push(entry.person);
+    }
+    Ok(persons)
+}
+
+fn do_set(name: String, key: String, value: String) {
     prepare_environments();
     let name = name.to_ascii_uppercase();
-    let tag = tag.to_ascii_uppercase();
-    match db_connection().execute(
-        "UPDATE video SET tag=? WHERE name=?",
-        rusqlite::params![&tag, &name],
-    ) {
-        Ok(n) => {
-            if n > 0 {
-                println!("'{name}' is tagged with '{tag}'");
-            } else {
-                eprintln!("failed to tag '{name}' with '{tag}', entry not found");
-            }
+    let key = key.to_ascii_lowercase();
+    match set_video_attr(&name, &key, &value) {
+        Ok(true) => {
+            println!("'{name}' has attribute '{key}={value}' now");
+        }
+        Ok(false) => {
+            eprintln!("failed to set attribute '{key}' of '{name}', entry not found");
         }
         Err(e) => {
-            eprintln!("failed to tag '{name}' with '{tag}': {e}");
+            eprintln!("failed to set attribute '{key}' of '{name}': {e}");
+        }
+    }
+}
+
+fn set_video_attr(name: &str, key: &str, value: &str) -> rusqlite::Result<bool> {
+    let tx = db_connection().unchecked_transaction()?;
+    let existed = tx
+        .query_row(
+            "SELECT 1 FROM video WHERE name=?",
+            rusqlite::params![name],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()?
+        .is_some();
+    if !existed {
+        return Ok(false);
+    }
+    VideoAttrEntry::delete_where(&tx, "WHERE name=? AND key=?", rusqlite::params![name, key])?;
+    let entry = VideoAttrEntry {
+        name: name.to_string(),
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    entry.insert(&tx)?;
+    tx.commit()?;
+    Ok(true)
+}
+
+fn do_unset(name: String, keys: Vec<String>) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    for key in keys {
+        let key = key.to_ascii_lowercase();
+        match VideoAttrEntry::delete_where(
+            db_connection(),
+            "WHERE name=? AND key=?",
+            rusqlite::params![&name, &key],
+        ) {
+            Ok(n) => {
+                if n > 0 {
+                    println!("attribute '{key}' is removed from '{name}'");
+                } else {
+                    eprintln!("'{name}' has no attribute '{key}'");
+                }
+            }
+            Err(e) => {
+                eprintln!("failed to remove attribute '{key}' from '{name}': {e}");
+            }
         }
     }
 }
@@ -494,11 +3039,28 @@ fn do_play(name: String) {
                 eprintln!("entry '{name}' not found");
             }
             Some(file_name) => {
-                std::process::Command::new("cmd.exe")
+                match std::process::Command::new("cmd.exe")
                     .arg("/C")
                     .arg("start")
                     .arg(format!("files/{file_name}"))
-                    .spawn().unwrap();
+                    .status()
+                {
+                    Ok(status) if status.success() => {}
+                    Ok(status) => {
+                        eprintln!("failed to play '{name}': {status}");
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("failed to play '{name}': {e}");
+                        return;
+                    }
+                }
+                match record_play(&name) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("failed to record the play of '{name}': {e}");
+                    }
+                }
             }
         },
         Err(e) => {
@@ -507,113 +3069,397 @@ fn do_play(name: String) {
     }
 }
 
+fn record_play(name: &str) -> rusqlite::Result<()> {
+    let tx = db_connection().unchecked_transaction()?;
+    let entry = PlayEventEntry {
+        video: name.to_string(),
+        played_at: unix_timestamp(),
+    };
+    entry.insert(&tx)?;
+    tx.execute(
+        "UPDATE video SET play_count=play_count+1, last_played=? WHERE name=?",
+        rusqlite::params![entry.played_at, name],
+    )?;
+    tx.commit()
+}
+
+fn do_history(name: Option<String>, limit: u64, offset: u64) {
+    prepare_environments();
+    let cols = PlayEventEntry::cols();
+    let mut query = Query::new();
+    if let Some(name) = name {
+        query = query.filter(cols.video.eq(name.to_ascii_uppercase()));
+    }
+    let query = query
+        .order_by(cols.played_at.desc())
+        .limit(limit)
+        .offset(offset);
+    let entries = PlayEventEntry::select_query(db_connection(), &query);
+    match entries {
+        Ok(entries) => {
+            for entry in entries {
+                let video = &entry.video;
+                let played_at = readable_age(entry.played_at);
+                println!("{played_at} {video}");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+        }
+    }
+}
+
+fn query_video_attrs() -> rusqlite::Result<HashMap<String, Vec<(String, String)>>> {
+    let entries = VideoAttrEntry::select(db_connection(), "ORDER BY name, key", [])?;
+    let mut attrs: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for entry in entries {
+        attrs
+            .entry(entry.name)
+            .or_default()
+            .push((entry.key, entry.value));
+    }
+    Ok(attrs)
+}
+
+/// Return the first line of each note, or the highlighted snippet of each matched note when searching.
+fn query_notes(search: Option<&str>) -> rusqlite::Result<HashMap<String, String>> {
+    let mut notes = HashMap::new();
+    let c = db_connection();
+    let mut stmt;
+    let mut rows = match search {
+        Some(search) => {
+            stmt = c.prepare(
+                "SELECT video, snippet(video_note, 1, '\x1b[1m', '\x1b[0m', '...', 16) \
+                FROM video_note WHERE video_note MATCH ?",
+            )?;
+            stmt.query(rusqlite::params![search])?
+        }
+        None => {
+            stmt = c.prepare("SELECT video, note FROM video_note")?;
+            stmt.query([])?
+        }
+    };
+    while let Some(row) = rows.next()? {
+        let video: String = row.get(0)?;
+        let note: String = row.get(1)?;
+        let note = match search {
+            Some(_) => note.replace('\n', " "),
+            None => note.lines().next().unwrap_or_default().to_string(),
+        };
+        notes.insert(video, note);
+    }
+    Ok(notes)
+}
+
+fn do_note(name: String) {
+    prepare_environments();
+    let name = name.to_ascii_uppercase();
+    let note: rusqlite::Result<Option<String>> = db_connection()
+        .query_row(
+            "SELECT IFNULL((SELECT note FROM video_note WHERE video=?1), '') FROM video WHERE name=?1",
+            rusqlite::params![&name],
+            |row| row.get(0),
+        )
+        .optional();
+    let note = match note {
+        Ok(Some(note)) => note,
+        Ok(None) => {
+            eprintln!("entry '{name}' not found");
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to query database: {e}");
+            return;
+        }
+    };
+
+    let path = std::env::temp_dir().join(format!("ddvm-note-{name}.txt"));
+    match std::fs::write(&path, &note) {
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("failed to write temporary file: {e}");
+            return;
+        }
+    }
+    let result = edit_file(&path).and_then(|_| std::fs::read_to_string(&path));
+    let _ = std::fs::remove_file(&path);
+    let note = match result {
+        Ok(s) => s.trim_end().to_string(),
+        Err(e) => {
+            eprintln!("failed to edit the notes of '{name}': {e}");
+            return;
+        }
+    };
+
+    match save_note(&name, &note) {
+        Ok(_) => {
+            if note.is_empty() {
+                println!("the notes of '{name}' are cleared");
+            } else {
+                println!("the notes of '{name}' are saved");
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to save the notes of '{name}': {e}");
+        }
+    }
+}
+
+fn edit_file(path: &std::path::Path) -> std::io::Result<()> {
+    let editor = match std::env::var("EDITOR") {
+        Ok(s) if !s.trim().is_empty() => s,
+        _ if cfg!(windows) => String::from("notepad.exe"),
+        _ => String::from("vi"),
+    };
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap();
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!(
+            "editor exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+fn save_note(name: &str, note: &str) -> rusqlite::Result<()> {
+    let tx = db_connection().unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM video_note WHERE video=?",
+        rusqlite::params![name],
+    )?;
+    if !note.is_empty() {
+        tx.execute(
+            "INSERT INTO video_note(video, note) VALUES (?, ?)",
+            rusqlite::params![name, note],
+        )?;
+    }
+    tx.commit()
+}
+
 fn do_list(filter_arg: FilterArg, verbose: bool, link: bool) {
     prepare_environments();
-    let (where_clause, where_params) = prepare_where_clause(&filter_arg);
-    let where_params: Vec<&dyn rusqlite::ToSql> = where_params
-        .iter()
-        .map(|s| s as &dyn rusqlite::ToSql)
-        .collect();
-    let where_params = where_params.as_slice();
+    let query = match prepare_query(&filter_arg) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
     if verbose {
-        do_list_verbosely(&where_clause, where_params, filter_arg.limit, link);
+        do_list_verbosely(&query, &filter_arg, link);
     } else {
-        do_list_briefly(&where_clause, where_params, filter_arg.limit, link);
+        do_list_briefly(&query, &filter_arg, link);
     }
 }
 
-fn do_list_verbosely<P: rusqlite::Params>(where_clause: &str, params: P, limit: usize, link: bool) {
-    let mut entries: Vec<VideoEntry> =
-        match VideoEntry::select(db_connection(), where_clause, params) {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("failed to query database: {}", e);
-                return;
-            }
-        };
-    if limit > 0 {
-        let mut rng = rand::thread_rng();
-        entries.shuffle(&mut rng);
-        entries.truncate(limit);
-        entries.sort_by(|a, b| a.name.cmp(&b.name));
+fn do_list_verbosely(query: &Query, filter_arg: &FilterArg, link: bool) {
+    let mut entries: Vec<VideoEntry> = match VideoEntry::select_query(db_connection(), query) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    if filter_arg.limit > 0 {
+        let limit = filter_arg.limit;
+        entries = pick_randomly(entries, limit, filter_arg.weighted, |e| e.rating);
     }
+    let video_tags = match query_video_tags() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    let attrs = match query_video_attrs() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    let persons = match query_video_persons() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
+    let notes = match query_notes(filter_arg.search.as_deref()) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
     for entry in &entries {
         let name = &entry.name;
-        let tag = &entry.tag;
         let file_name = &entry.file_name;
         let file_size = readable_file_size(entry.file_size);
         let duration = readable_duration(entry.duration);
         let video_codec = &entry.video_codec;
         let video_bit_rate = entry.video_bit_rate;
-        let video_frame_rate = entry.video_frame_rate;
+        let video_frame_rate = readable_frame_rate(entry.video_frame_rate);
         let video_width = entry.video_width;
         let video_height = entry.video_height;
         let audio_codec = &entry.audio_codec;
         let audio_bit_rate = entry.audio_bit_rate;
-        println!("{name}[{tag}] {duration} {file_name}/{file_size}");
+        let rating = readable_rating(entry.rating, entry.favorite);
+        println!("{name} {duration} {file_name}/{file_size}{rating}");
+        if let Some(tags) = video_tags.get(name) {
+            for (namespace, tags) in group_tags_by_namespace(tags) {
+                let namespace = if namespace.is_empty() {
+                    String::from("tags")
+                } else {
+                    namespace.to_ascii_lowercase()
+                };
+                let tags = tags.join(",");
+                println!("  {namespace}: {tags}");
+            }
+        }
+        if let Some(persons) = persons.get(name) {
+            let persons = persons.join(", ");
+            println!("  persons: {persons}");
+        }
         println!("  video: codec={video_codec}, bit_rate={video_bit_rate}kbps, frame_rate={video_frame_rate}fps, resolution={video_width}x{video_height}");
         println!("  audio: codec={audio_codec}, bit_rate={audio_bit_rate}kbps");
-    }
-    if !entries.is_empty() && link {
-        match clear_directory("links") {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("failed to clear directory './links/': {e}");
-                return;
-            }
+        let play_count = entry.play_count;
+        let last_played = readable_age(entry.last_played);
+        println!("  played: count={play_count}, last={last_played}");
+        if let Some(attrs) = attrs.get(name) {
+            let attrs: Vec<String> = attrs.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            let attrs = attrs.join(", ");
+            println!("  attrs: {attrs}");
         }
-        for entry in &entries {
-            let file_name = &entry.file_name;
-            match make_link(&file_name) {
-                Ok(_) => {}
-                Err(e) => {
-                    eprintln!("failed to make link for '{file_name}': {e}")
-                }
-            }
+        if let Some(note) = notes.get(name) {
+            println!("  note: {note}");
         }
     }
+    if !entries.is_empty() && link {
+        let entries = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.file_name.as_str()));
+        make_links(entries, filter_arg.collection.as_deref());
+    }
 }
 
-fn do_list_briefly<P: rusqlite::Params>(where_clause: &str, params: P, limit: usize, link: bool) {
+fn do_list_briefly(query: &Query, filter_arg: &FilterArg, link: bool) {
     let mut entries: Vec<BriefVideoEntry> =
-        match BriefVideoEntry::select(db_connection(), where_clause, params) {
+        match BriefVideoEntry::select_query(db_connection(), query) {
             Ok(v) => v,
             Err(e) => {
                 eprintln!("failed to query database: {}", e);
                 return;
             }
         };
-    if limit > 0 {
-        let mut rng = rand::thread_rng();
-        entries.shuffle(&mut rng);
-        entries.truncate(limit);
-        entries.sort_by(|a, b| a.name.cmp(&b.name));
+    if filter_arg.limit > 0 {
+        let limit = filter_arg.limit;
+        entries = pick_randomly(entries, limit, filter_arg.weighted, |e| e.rating);
     }
+    let video_tags = match query_video_tags() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to query database: {}", e);
+            return;
+        }
+    };
     for entry in &entries {
         let name = &entry.name;
-        let tag = &entry.tag;
+        let tag = video_tags
+            .get(name)
+            .map(|tags| tags.join(","))
+            .unwrap_or_default();
         let file_name = &entry.file_name;
         let file_size = readable_file_size(entry.file_size);
         let duration = readable_duration(entry.duration);
-        println!("{name}[{tag}] {duration} {file_name}/{file_size}");
+        let rating = readable_rating(entry.rating, entry.favorite);
+        println!("{name}[{tag}] {duration} {file_name}/{file_size}{rating}");
     }
     if !entries.is_empty() && link {
-        match clear_directory("links") {
-            Ok(_) => {}
+        let entries = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.file_name.as_str()));
+        make_links(entries, filter_arg.collection.as_deref());
+    }
+}
+
+fn do_db_migrate(dry_run: bool) {
+    enter_program_directory();
+    let mut c = match rusqlite::Connection::open("database") {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("failed to open database: {e}");
+            return;
+        }
+    };
+    if dry_run {
+        let planned = match migration::plan(&mut c) {
+            Ok(v) => v,
             Err(e) => {
-                eprintln!("failed to clear directory './links/': {e}");
+                eprintln!("{e}");
                 return;
             }
+        };
+        if planned.is_empty() {
+            println!("database is up to date");
         }
-        for entry in &entries {
-            let file_name = &entry.file_name;
-            match make_link(&file_name) {
-                Ok(_) => {}
-                Err(e) => {
-                    eprintln!("failed to make link for '{file_name}': {e}")
+        for step in planned {
+            let version = step.migration.version;
+            let description = step.migration.description;
+            println!("-- {version}: {description}");
+            for note in step.notes {
+                println!("-- {note}");
+            }
+            for statement in step.statements {
+                println!("{statement};");
+            }
+        }
+        return;
+    }
+    match migration::migrate(&mut c) {
+        Ok(applied) => {
+            if applied.is_empty() {
+                println!("database is up to date");
+            }
+            for step in applied {
+                let version = step.migration.version;
+                let description = step.migration.description;
+                println!("migration {version} is applied: {description}");
+                for note in step.notes {
+                    println!("  {note}");
                 }
             }
         }
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    }
+    let diffs = match sync_schemas(&mut c) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to synchronize schema: {e}");
+            return;
+        }
+    };
+    for diff in diffs {
+        let table = diff.table;
+        if diff.created {
+            println!("table '{table}' is created");
+        }
+        for column in &diff.added {
+            println!("column '{column}' is added to table '{table}'");
+        }
+        for problem in diff.problems() {
+            eprintln!("{problem}; a migration is needed");
+        }
+    }
+    if let Err(e) = create_indexes(&mut c) {
+        eprintln!("failed to create indexes: {e}");
     }
 }
 
@@ -664,10 +3510,7 @@ fn do_check(fix: bool) {
     for name in db_file_names.difference(&fs_file_names) {
         println!("'{name}' exists in database, but not in file system");
         if fix {
-            match db_connection().execute(
-                "DELETE FROM video WHERE file_name=?",
-                rusqlite::params![name],
-            ) {
+            match delete_video_by_file_name(name) {
                 Ok(_) => {
                     println!("invalid entry '{name}' removed");
                 }
@@ -678,10 +3521,133 @@ fn do_check(fix: bool) {
         }
     }
 
+    let rules = if fix {
+        match naming::load_rules() {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        }
+    } else {
+        vec![]
+    };
+    // The tags of `entries` in order.
+    let mut entry_tags: Vec<Vec<String>> = vec![];
+    let mut entries: Vec<VideoEntry> = vec![];
     for name in fs_file_names.difference(&db_file_names) {
         println!("'{name}' exists in file system, but not in database");
-        if fix {
-            do_add_file("files", name);
+        if !fix {
+            continue;
+        }
+        let extracted = match naming::extract(&rules, name) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("skip file '{name}'; {e}");
+                continue;
+            }
+        };
+        let entry = match read_video_file("files", name, extracted.name) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let existed = db_connection()
+            .query_row(
+                "SELECT 1 FROM video WHERE name=?",
+                rusqlite::params![&entry.name],
+                |row| row.get::<_, u32>(0),
+            )
+            .optional();
+        match existed {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                eprintln!(
+                    "skip file '{name}'; an entry with name '{}' already existed in database",
+                    entry.name
+                );
+                continue;
+            }
+            Err(e) => {
+                eprintln!("failed to query database: {e}");
+                return;
+            }
         }
+        if let Some(other) = entries.iter().find(|e| e.name == entry.name) {
+            eprintln!(
+                "skip file '{name}'; an entry with name '{}' is added from file '{}'",
+                entry.name, other.file_name
+            );
+            continue;
+        }
+        print_video_file(&entry);
+        entry_tags.push(extracted.tags);
+        entries.push(entry);
+    }
+    if let Err(e) = VideoEntry::insert_many(db_connection(), &entries) {
+        eprintln!("failed to add files: {e}");
+        return;
+    }
+    for (entry, tags) in entries.iter().zip(&entry_tags) {
+        for tag in tags {
+            tag_added_video(&entry.name, tag);
+        }
+    }
+}
+
+fn delete_video_by_file_name(file_name: &str) -> rusqlite::Result<()> {
+    let tx = db_connection().unchecked_transaction()?;
+    let params = rusqlite::params![file_name];
+    // The notes are in a virtual table, which cannot reference the video.
+    tx.execute(
+        "DELETE FROM video_note WHERE video IN (SELECT name FROM video WHERE file_name=?)",
+        params,
+    )?;
+    // Removed from the collections first to close the gaps in their positions.
+    let collections = CollectionVideoEntry::select(
+        &tx,
+        "WHERE video IN (SELECT name FROM video WHERE file_name=?)",
+        params,
+    )?;
+    for entry in collections {
+        remove_collection_video(&tx, &entry.collection, &entry.video)?;
+    }
+    // The others are deleted in cascade.
+    VideoEntry::delete_where(&tx, "WHERE file_name=?", params)?;
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn age_units() {
+        assert_eq!("90s".parse::<AgeArg>().unwrap().0, 90);
+        assert_eq!("2w".parse::<AgeArg>().unwrap().0, 2 * 7 * 24 * 60 * 60);
+        assert!("99999999999999999w".parse::<AgeArg>().is_err());
+        assert!("-1d".parse::<AgeArg>().is_err());
+        assert!("5".parse::<AgeArg>().is_err());
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_matches("*.mp4", "x-1.mp4"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("x*1*.mp4", "x-1-1.mp4"));
+        assert!(glob_matches("*-*-*", "a-b-c"));
+        assert!(!glob_matches("*.mp4", "x-1.mp4.part"));
+        assert!(!glob_matches("*-*-*", "a-b"));
+        assert!(glob_matches("x-?.mp4", "x-1.mp4"));
+        assert!(!glob_matches("x-?.mp4", "x-12.mp4"));
+        assert!(!glob_matches("x-?.mp4", "x-.mp4"));
+        assert!(glob_matches("x-1.mp4", "x-1.mp4"));
+        assert!(!glob_matches("x-1.mp4", "x-1.mp"));
+    }
+
+    #[test]
+    fn glob_ignores_case() {
+        assert!(glob_matches("*.MP4", "x-1.mp4"));
+        assert!(glob_matches("X-?.mp4", "x-1.MP4"));
+        assert!(glob_matches("*.mkv", "Été.MKV"));
     }
 }
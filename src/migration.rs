@@ -0,0 +1,453 @@
+use rusqlite::types::Value;
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    up: fn(&mut Step) -> rusqlite::Result<()>,
+}
+
+/// The statements executed by a migration, recorded so that they can be printed.
+pub struct Step<'a> {
+    c: &'a rusqlite::Connection,
+    statements: Vec<String>,
+    /// The changes of user data worth telling, e.g. deleted rows.
+    notes: Vec<String>,
+}
+
+impl Step<'_> {
+    fn execute(&mut self, sql: &str) -> rusqlite::Result<()> {
+        self.c.execute_batch(sql)?;
+        self.statements.push(sql.to_string());
+        Ok(())
+    }
+
+    /// Execute the statement with bound parameters, recorded along with their values.
+    fn execute_with(&mut self, sql: &str, params: &[Value]) -> rusqlite::Result<()> {
+        self.c.execute(sql, rusqlite::params_from_iter(params))?;
+        let values: Vec<String> = params
+            .iter()
+            .map(|v| match v {
+                Value::Integer(n) => n.to_string(),
+                Value::Text(s) => format!("'{s}'"),
+                v => format!("{v:?}"),
+            })
+            .collect();
+        self.statements
+            .push(format!("-- parameters: {}\n{sql}", values.join(", ")));
+        Ok(())
+    }
+
+    fn column_exists(&self, table: &str, column: &str) -> rusqlite::Result<bool> {
+        self.c.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name=?",
+            rusqlite::params![table, column],
+            |row| row.get(0),
+        )
+    }
+
+    /// Databases created before the migrations were versioned may already have the column.
+    fn add_column(
+        &mut self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> rusqlite::Result<bool> {
+        if self.column_exists(table, column)? {
+            return Ok(false);
+        }
+        self.execute(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))?;
+        Ok(true)
+    }
+}
+
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the video table",
+        up: |s| {
+            s.execute(
+                "CREATE TABLE IF NOT EXISTS video(name TEXT NOT NULL PRIMARY KEY, tag TEXT NOT NULL, \
+                file_name TEXT NOT NULL, file_size INTEGER NOT NULL, duration INTEGER NOT NULL, \
+                video_codec TEXT NOT NULL, video_bit_rate INTEGER NOT NULL, video_frame_rate INTEGER NOT NULL, \
+                video_width INTEGER NOT NULL, video_height INTEGER NOT NULL, \
+                audio_codec TEXT NOT NULL, audio_bit_rate INTEGER NOT NULL)",
+            )?;
+            if s.column_exists("video", "tag")? {
+                s.execute("CREATE INDEX IF NOT EXISTS idx_video_tag ON video(tag)")?;
+            }
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_duration ON video(duration)")
+        },
+    },
+    Migration {
+        version: 2,
+        description: "move the single tag of videos into video_tag",
+        up: |s| {
+            s.execute("CREATE TABLE IF NOT EXISTS tag(name TEXT NOT NULL PRIMARY KEY)")?;
+            s.execute(
+                "CREATE TABLE IF NOT EXISTS video_tag(video TEXT NOT NULL, tag TEXT NOT NULL)",
+            )?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_tag_video ON video_tag(video)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_tag_tag ON video_tag(tag)")?;
+            if s.column_exists("video", "tag")? {
+                s.execute(
+                    "INSERT OR IGNORE INTO tag(name) SELECT DISTINCT tag FROM video WHERE tag<>''",
+                )?;
+                s.execute(
+                    "INSERT INTO video_tag(video, tag) SELECT name, tag FROM video WHERE tag<>''",
+                )?;
+                s.execute("DROP INDEX IF EXISTS idx_video_tag")?;
+                s.execute("ALTER TABLE video DROP COLUMN tag")?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 3,
+        description: "add namespaces to tags",
+        up: |s| {
+            if s.add_column("tag", "namespace", "TEXT NOT NULL DEFAULT ''")? {
+                s.execute("UPDATE tag SET namespace=substr(name, 1, instr(name, ':')-1)")?;
+            }
+            s.execute("CREATE INDEX IF NOT EXISTS idx_tag_namespace ON tag(namespace)")
+        },
+    },
+    Migration {
+        version: 4,
+        description: "add aliases of tags",
+        up: |s| {
+            s.execute("CREATE TABLE IF NOT EXISTS tag_alias(alias TEXT NOT NULL PRIMARY KEY, tag TEXT NOT NULL)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_tag_alias_tag ON tag_alias(tag)")
+        },
+    },
+    Migration {
+        version: 5,
+        description: "add parents of tags",
+        up: |s| {
+            s.execute("CREATE TABLE IF NOT EXISTS tag_parent(tag TEXT NOT NULL PRIMARY KEY, parent TEXT NOT NULL)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_tag_parent_parent ON tag_parent(parent)")
+        },
+    },
+    Migration {
+        version: 6,
+        description: "add ratings and favorites of videos",
+        up: |s| {
+            s.add_column("video", "rating", "INTEGER NOT NULL DEFAULT 0")?;
+            s.add_column("video", "favorite", "INTEGER NOT NULL DEFAULT 0")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_rating ON video(rating)")
+        },
+    },
+    Migration {
+        version: 7,
+        description: "record plays of videos",
+        up: |s| {
+            s.add_column("video", "play_count", "INTEGER NOT NULL DEFAULT 0")?;
+            s.add_column("video", "last_played", "INTEGER NOT NULL DEFAULT 0")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_last_played ON video(last_played)")?;
+            s.execute("CREATE TABLE IF NOT EXISTS play_event(video TEXT NOT NULL, played_at INTEGER NOT NULL)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_play_event_video ON play_event(video)")?;
+            s.execute(
+                "CREATE INDEX IF NOT EXISTS idx_play_event_played_at ON play_event(played_at)",
+            )
+        },
+    },
+    Migration {
+        version: 8,
+        description: "add notes of videos",
+        up: |s| {
+            s.execute(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS video_note USING fts5(video UNINDEXED, note)",
+            )
+        },
+    },
+    Migration {
+        version: 9,
+        description: "add custom attributes of videos",
+        up: |s| {
+            s.execute("CREATE TABLE IF NOT EXISTS video_attr(name TEXT NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_attr_name ON video_attr(name)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_attr_key ON video_attr(key)")
+        },
+    },
+    Migration {
+        version: 10,
+        description: "add collections of videos",
+        up: |s| {
+            s.execute("CREATE TABLE IF NOT EXISTS collection(name TEXT NOT NULL PRIMARY KEY)")?;
+            s.execute("CREATE TABLE IF NOT EXISTS collection_video(collection TEXT NOT NULL, video TEXT NOT NULL, position INTEGER NOT NULL)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_collection_video_collection ON collection_video(collection)")?;
+            s.execute(
+                "CREATE INDEX IF NOT EXISTS idx_collection_video_video ON collection_video(video)",
+            )
+        },
+    },
+    Migration {
+        version: 11,
+        description: "add persons linked to videos",
+        up: |s| {
+            s.execute("CREATE TABLE IF NOT EXISTS person(name TEXT NOT NULL PRIMARY KEY, note TEXT NOT NULL, portrait TEXT NOT NULL)")?;
+            s.execute("CREATE TABLE IF NOT EXISTS person_alias(alias TEXT NOT NULL PRIMARY KEY, person TEXT NOT NULL)")?;
+            s.execute(
+                "CREATE INDEX IF NOT EXISTS idx_person_alias_person ON person_alias(person)",
+            )?;
+            s.execute("CREATE TABLE IF NOT EXISTS video_person(video TEXT NOT NULL, person TEXT NOT NULL)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_person_video ON video_person(video)")?;
+            s.execute("CREATE INDEX IF NOT EXISTS idx_video_person_person ON video_person(person)")
+        },
+    },
+    Migration {
+        version: 12,
+        description: "re-read the sizes of files which were truncated to 32 bits",
+        up: reread_file_sizes,
+    },
+    Migration {
+        version: 13,
+        description: "store frame rates of videos as real numbers",
+        up: |s| {
+            let columns = "name, file_name, file_size, duration, video_codec, video_bit_rate, video_frame_rate, \
+                video_width, video_height, audio_codec, audio_bit_rate, rating, favorite, play_count, last_played";
+            s.execute(
+                "CREATE TABLE video_new(name TEXT NOT NULL PRIMARY KEY, \
+                file_name TEXT NOT NULL, file_size INTEGER NOT NULL, duration INTEGER NOT NULL, \
+                video_codec TEXT NOT NULL, video_bit_rate INTEGER NOT NULL, video_frame_rate REAL NOT NULL, \
+                video_width INTEGER NOT NULL, video_height INTEGER NOT NULL, \
+                audio_codec TEXT NOT NULL, audio_bit_rate INTEGER NOT NULL, \
+                rating INTEGER NOT NULL, favorite INTEGER NOT NULL, \
+                play_count INTEGER NOT NULL, last_played INTEGER NOT NULL)",
+            )?;
+            s.execute(&format!(
+                "INSERT INTO video_new({columns}) SELECT {columns} FROM video"
+            ))?;
+            s.execute("DROP TABLE video")?;
+            s.execute("ALTER TABLE video_new RENAME TO video")?;
+            s.execute("CREATE INDEX idx_video_duration ON video(duration)")?;
+            s.execute("CREATE INDEX idx_video_rating ON video(rating)")?;
+            s.execute("CREATE INDEX idx_video_last_played ON video(last_played)")
+        },
+    },
+    Migration {
+        version: 14,
+        description: "remove duplicate links of videos before they are indexed as unique",
+        up: |s| {
+            for (table, columns) in [
+                ("video_tag", "video, tag"),
+                ("video_person", "video, person"),
+                ("video_attr", "name, key"),
+                ("collection_video", "collection, video"),
+            ] {
+                s.execute(&format!(
+                    "DELETE FROM {table} WHERE rowid NOT IN (SELECT MIN(rowid) FROM {table} GROUP BY {columns})"
+                ))?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 15,
+        description:
+            "add foreign keys to the tables referencing videos, tags, collections and persons",
+        up: |s| {
+            // The indexes dropped with the tables are recreated by `create_indexes` of the entries.
+            rebuild_table(
+                s,
+                "play_event",
+                "video TEXT NOT NULL, played_at INTEGER NOT NULL, \
+                FOREIGN KEY(video) REFERENCES video(name) ON DELETE CASCADE",
+                "video, played_at",
+                "video IN (SELECT name FROM video)",
+            )?;
+            rebuild_table(
+                s,
+                "video_attr",
+                "name TEXT NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL, \
+                FOREIGN KEY(name) REFERENCES video(name) ON DELETE CASCADE",
+                "name, key, value",
+                "name IN (SELECT name FROM video)",
+            )?;
+            rebuild_table(
+                s,
+                "collection_video",
+                "collection TEXT NOT NULL, video TEXT NOT NULL, position INTEGER NOT NULL, \
+                FOREIGN KEY(collection) REFERENCES collection(name) ON DELETE CASCADE, \
+                FOREIGN KEY(video) REFERENCES video(name) ON DELETE CASCADE",
+                "collection, video, position",
+                "collection IN (SELECT name FROM collection) AND video IN (SELECT name FROM video)",
+            )?;
+            rebuild_table(
+                s,
+                "person_alias",
+                "alias TEXT NOT NULL PRIMARY KEY, person TEXT NOT NULL, \
+                FOREIGN KEY(person) REFERENCES person(name) ON DELETE CASCADE",
+                "alias, person",
+                "person IN (SELECT name FROM person)",
+            )?;
+            rebuild_table(
+                s,
+                "video_person",
+                "video TEXT NOT NULL, person TEXT NOT NULL, \
+                FOREIGN KEY(video) REFERENCES video(name) ON DELETE CASCADE, \
+                FOREIGN KEY(person) REFERENCES person(name) ON DELETE CASCADE",
+                "video, person",
+                "video IN (SELECT name FROM video) AND person IN (SELECT name FROM person)",
+            )?;
+            rebuild_table(
+                s,
+                "tag_alias",
+                "alias TEXT NOT NULL PRIMARY KEY, tag TEXT NOT NULL, \
+                FOREIGN KEY(tag) REFERENCES tag(name) ON DELETE CASCADE",
+                "alias, tag",
+                "tag IN (SELECT name FROM tag)",
+            )?;
+            rebuild_table(
+                s,
+                "tag_parent",
+                "tag TEXT NOT NULL PRIMARY KEY, parent TEXT NOT NULL, \
+                FOREIGN KEY(tag) REFERENCES tag(name) ON DELETE CASCADE, \
+                FOREIGN KEY(parent) REFERENCES tag(name) ON DELETE CASCADE",
+                "tag, parent",
+                "tag IN (SELECT name FROM tag) AND parent IN (SELECT name FROM tag)",
+            )?;
+            rebuild_table(
+                s,
+                "video_tag",
+                "video TEXT NOT NULL, tag TEXT NOT NULL, \
+                FOREIGN KEY(video) REFERENCES video(name) ON DELETE CASCADE, \
+                FOREIGN KEY(tag) REFERENCES tag(name) ON DELETE CASCADE",
+                "video, tag",
+                "video IN (SELECT name FROM video) AND tag IN (SELECT name FROM tag)",
+            )
+        },
+    },
+];
+
+/// Rebuild the table with the new definition, keeping the rows matching `filter`.
+/// The dropped rows are counted in the notes.
+fn rebuild_table(
+    s: &mut Step,
+    table: &str,
+    definition: &str,
+    columns: &str,
+    filter: &str,
+) -> rusqlite::Result<()> {
+    let dropped: u64 = s.c.query_row(
+        &format!("SELECT COUNT(*) FROM {table} WHERE NOT ({filter})"),
+        [],
+        |row| row.get(0),
+    )?;
+    if dropped > 0 {
+        s.notes.push(format!(
+            "{dropped} row(s) of table '{table}' referencing missing rows are dropped"
+        ));
+    }
+    s.execute(&format!("CREATE TABLE {table}_new({definition})"))?;
+    s.execute(&format!(
+        "INSERT INTO {table}_new({columns}) SELECT {columns} FROM {table} WHERE {filter}"
+    ))?;
+    s.execute(&format!("DROP TABLE {table}"))?;
+    s.execute(&format!("ALTER TABLE {table}_new RENAME TO {table}"))
+}
+
+fn reread_file_sizes(s: &mut Step) -> rusqlite::Result<()> {
+    let entries: Vec<(String, String, i64)> = {
+        let mut stmt =
+            s.c.prepare("SELECT name, file_name, file_size FROM video")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    for (name, file_name, file_size) in entries {
+        // The missing files are reported by `check` instead.
+        let md = match std::fs::metadata(format!("files/{file_name}")) {
+            Ok(md) => md,
+            Err(_) => continue,
+        };
+        let size = md.len() as i64;
+        if size != file_size {
+            s.execute_with(
+                "UPDATE video SET file_size=? WHERE name=?",
+                &[Value::Integer(size), Value::Text(name)],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn current_version(c: &rusqlite::Connection) -> rusqlite::Result<u32> {
+    c.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+fn pending_migrations(c: &rusqlite::Connection) -> Result<&'static [Migration], String> {
+    let version = current_version(c).map_err(|e| format!("failed to query database: {e}"))?;
+    let latest = latest_version();
+    if version > latest {
+        return Err(format!(
+            "database is at version {version}, newer than the supported version {latest}"
+        ));
+    }
+    let start = MIGRATIONS.partition_point(|m| m.version <= version);
+    Ok(&MIGRATIONS[start..])
+}
+
+/// An applied migration with the statements it executed and its notes.
+pub struct Applied {
+    pub migration: &'static Migration,
+    pub statements: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+fn apply(c: &rusqlite::Connection, migration: &'static Migration) -> rusqlite::Result<Applied> {
+    let mut step = Step {
+        c,
+        statements: vec![],
+        notes: vec![],
+    };
+    (migration.up)(&mut step)?;
+    step.execute(&format!("PRAGMA user_version={}", migration.version))?;
+    Ok(Applied {
+        migration,
+        statements: step.statements,
+        notes: step.notes,
+    })
+}
+
+/// Apply the pending migrations in order, each in its own transaction.
+/// Return the applied migrations.
+pub fn migrate(c: &mut rusqlite::Connection) -> Result<Vec<Applied>, String> {
+    let mut applied = vec![];
+    for migration in pending_migrations(c)? {
+        let version = migration.version;
+        let description = migration.description;
+        let step = c
+            .transaction()
+            .and_then(|tx| {
+                let step = apply(&tx, migration)?;
+                tx.commit()?;
+                Ok(step)
+            })
+            .map_err(|e| format!("failed to apply migration {version} ({description}): {e}"))?;
+        applied.push(step);
+    }
+    Ok(applied)
+}
+
+/// Apply the pending migrations in a transaction which is rolled back afterward.
+/// Return the pending migrations as they would be applied.
+pub fn plan(c: &mut rusqlite::Connection) -> Result<Vec<Applied>, String> {
+    let pending = pending_migrations(c)?;
+    let tx = c
+        .transaction()
+        .map_err(|e| format!("failed to begin transaction: {e}"))?;
+    let mut planned = vec![];
+    for migration in pending {
+        let version = migration.version;
+        let description = migration.description;
+        let step = apply(&tx, migration)
+            .map_err(|e| format!("failed to apply migration {version} ({description}): {e}"))?;
+        planned.push(step);
+    }
+    Ok(planned)
+}
@@ -0,0 +1,222 @@
+use regex::{Regex, RegexBuilder};
+
+/// The file of the rules to extract names from file names, one regular expression per line.
+/// Blank lines and lines starting with `#` are ignored. The rules ignore case, like the names
+/// they extract. The first rule matching the file name with a non-empty group `name` is used:
+/// the group is the name of the video, the optional group `part` is the part number, and every
+/// group whose name starts with `tag` is a tag, e.g.
+/// `^(?P<name>[a-z]+-\d+)(?:\.part(?P<part>\d+))?(?:\.(?P<tag>1080p|720p))?\.\w+$`
+pub const RULES_FILE: &str = "name_rules.txt";
+
+pub struct Rule {
+    pub line: usize,
+    pub regex: Regex,
+}
+
+pub struct Extracted<'a> {
+    /// The matched rule; `None` if the name is derived by default.
+    pub rule: Option<&'a Rule>,
+    pub name: String,
+    pub part: Option<u32>,
+    pub tags: Vec<String>,
+}
+
+fn compile_rule(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// Load the rules from `RULES_FILE` in the current directory; no rules if it does not exist.
+pub fn load_rules() -> Result<Vec<Rule>, String> {
+    let content = match std::fs::read_to_string(RULES_FILE) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("failed to read '{RULES_FILE}': {e}")),
+    };
+    let mut rules = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let regex = compile_rule(line)
+            .map_err(|e| format!("invalid rule at line {line_number} of '{RULES_FILE}': {e}"))?;
+        if !regex.capture_names().any(|n| n == Some("name")) {
+            return Err(format!(
+                "invalid rule at line {line_number} of '{RULES_FILE}': missing group 'name'"
+            ));
+        }
+        rules.push(Rule {
+            line: line_number,
+            regex,
+        });
+    }
+    Ok(rules)
+}
+
+/// Extract the name of the video from its file name by the first matching rule, or by default
+/// from the part before the first '.'. A part number is appended to the name as '.PART<N>'.
+pub fn extract<'a>(rules: &'a [Rule], file_name: &str) -> Result<Extracted<'a>, String> {
+    for rule in rules {
+        let captures = match rule.regex.captures(file_name) {
+            Some(c) => c,
+            None => continue,
+        };
+        // The group may not take part in the match, e.g. in an alternation.
+        let mut name = match captures.name("name") {
+            Some(m) => canonical_name(m.as_str()),
+            None => continue,
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let part = match captures.name("part") {
+            Some(m) => match m.as_str().parse::<u32>() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    return Err(format!(
+                        "part '{}' matched by rule at line {} is not a number",
+                        m.as_str(),
+                        rule.line
+                    ))
+                }
+            },
+            None => None,
+        };
+        if let Some(part) = part {
+            name.push_str(&format!(".PART{part}"));
+        }
+        let tags = rule
+            .regex
+            .capture_names()
+            .flatten()
+            .filter(|n| n.starts_with("tag"))
+            .filter_map(|n| captures.name(n))
+            .filter(|m| !m.as_str().is_empty())
+            .map(|m| m.as_str().to_ascii_uppercase())
+            .collect();
+        return Ok(Extracted {
+            rule: Some(rule),
+            name,
+            part,
+            tags,
+        });
+    }
+    let base_name = file_name.split('.').next().unwrap();
+    let name = canonical_name(base_name);
+    if name.is_empty() {
+        return Err(String::from("no name before the first '.'"));
+    }
+    Ok(Extracted {
+        rule: None,
+        name,
+        part: None,
+        tags: vec![],
+    })
+}
+
+fn canonical_name(s: &str) -> String {
+    s.chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(patterns: &[&str]) -> Vec<Rule> {
+        patterns
+            .iter()
+            .enumerate()
+            .map(|(i, p)| Rule {
+                line: i + 1,
+                regex: compile_rule(p).unwrap(),
+            })
+            .collect()
+    }
+
+    const RULE: &str =
+        r"^(?P<name>[a-z]+-\d+)(?:\.part(?P<part>\d+))?(?:\.(?P<tag>1080p|720p))?\.\w+$";
+
+    #[test]
+    fn part_suffix() {
+        let rules = rules(&[RULE]);
+        let extracted = extract(&rules, "abc-123.part2.mp4").unwrap();
+        assert_eq!(extracted.rule.map(|r| r.line), Some(1));
+        assert_eq!(extracted.name, "ABC123.PART2");
+        assert_eq!(extracted.part, Some(2));
+        assert!(extracted.tags.is_empty());
+
+        let extracted = extract(&rules, "abc-123.mp4").unwrap();
+        assert_eq!(extracted.name, "ABC123");
+        assert_eq!(extracted.part, None);
+    }
+
+    #[test]
+    fn ignores_case() {
+        let rules = rules(&[RULE]);
+        let extracted = extract(&rules, "ABC-123.PART2.1080P.MP4").unwrap();
+        assert_eq!(extracted.rule.map(|r| r.line), Some(1));
+        assert_eq!(extracted.name, "ABC123.PART2");
+        assert_eq!(extracted.part, Some(2));
+        assert_eq!(extracted.tags, ["1080P"]);
+    }
+
+    #[test]
+    fn tag_groups() {
+        let rules = rules(&[
+            r"^(?P<tag_studio>[a-z]+)_(?P<name>\w+-\d+)(?:\.(?P<tag>1080p|720p))?(?P<tag_empty>x*)\.\w+$",
+        ]);
+        let extracted = extract(&rules, "studio_abc-1.1080p.mp4").unwrap();
+        assert_eq!(extracted.name, "ABC1");
+        assert_eq!(extracted.tags, ["STUDIO", "1080P"]);
+
+        let extracted = extract(&rules, "studio_abc-1.mp4").unwrap();
+        assert_eq!(extracted.tags, ["STUDIO"]);
+    }
+
+    #[test]
+    fn fallback() {
+        let rules = rules(&[RULE]);
+        let extracted = extract(&rules, "Foo_Bar-7.part1.mkv").unwrap();
+        assert!(extracted.rule.is_none());
+        assert_eq!(extracted.name, "FOO_BAR7");
+        assert_eq!(extracted.part, None);
+        assert!(extracted.tags.is_empty());
+
+        assert_eq!(extract(&[], "abc-1.mp4").unwrap().name, "ABC1");
+        assert!(extract(&[], ".mp4").is_err());
+        assert!(extract(&[], "-.mp4").is_err());
+    }
+
+    #[test]
+    fn missing_or_empty_name() {
+        let rules = rules(&[
+            r"^(?:(?P<name>[a-z]+-\d+)|y)\.\w+$",
+            r"^(?P<name>x*)(?P<tag>y)\.\w+$",
+            r"^(?P<name>-*)z\.\w+$",
+            r"^(?P<name>\w)\w*\.\w+$",
+        ]);
+        let extracted = extract(&rules, "y.mp4").unwrap();
+        assert_eq!(extracted.rule.map(|r| r.line), Some(4));
+        assert_eq!(extracted.name, "Y");
+        assert!(extracted.tags.is_empty());
+
+        // '-' is an empty name once canonicalized.
+        let extracted = extract(&rules, "-z.mp4").unwrap();
+        assert!(extracted.rule.is_none());
+        assert_eq!(extracted.name, "Z");
+        assert_eq!(
+            extract(&rules, "abc-1.mp4").unwrap().rule.map(|r| r.line),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn invalid_part() {
+        let rules = rules(&[r"^(?P<name>[a-z]+)\.part(?P<part>\w+)\.\w+$"]);
+        assert!(extract(&rules, "abc.partx.mp4").is_err());
+    }
+}
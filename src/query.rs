@@ -0,0 +1,525 @@
+use runtime::dsl::{Column, Condition};
+use rusqlite::types::Value;
+
+pub enum Expr {
+    Tag(String),
+    Compare(&'static Field, &'static str, Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+pub struct Field {
+    name: &'static str,
+    kind: FieldKind,
+}
+
+#[derive(PartialEq)]
+enum FieldKind {
+    Text,
+    Integer,
+    Real,
+    Duration,
+}
+
+static FIELDS: &[Field] = &[
+    Field {
+        name: "name",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "file_name",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "file_size",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "duration",
+        kind: FieldKind::Duration,
+    },
+    Field {
+        name: "video_codec",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "video_bit_rate",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "video_frame_rate",
+        kind: FieldKind::Real,
+    },
+    Field {
+        name: "video_width",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "video_height",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "audio_codec",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "audio_bit_rate",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "rating",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "favorite",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "play_count",
+        kind: FieldKind::Integer,
+    },
+];
+
+pub struct ParseError {
+    column: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn report(&self, source: &str) -> String {
+        let padding = " ".repeat(self.column);
+        format!("{}\n  {source}\n  {padding}^", self.message)
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    Operator(&'static str),
+    Word(String),
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::LeftParen => String::from("'('"),
+            Token::RightParen => String::from("')'"),
+            Token::And => String::from("'and'"),
+            Token::Or => String::from("'or'"),
+            Token::Not => String::from("'not'"),
+            Token::Operator(op) => format!("'{op}'"),
+            Token::Word(w) => format!("'{w}'"),
+            Token::End => String::from("end of query"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => {
+                tokens.push((start, Token::LeftParen));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::RightParen));
+                i += 1;
+            }
+            '=' | '!' | '<' | '>' => {
+                let next = chars.get(i + 1).copied();
+                let op = match (c, next) {
+                    ('=', Some('=')) => "==",
+                    ('!', Some('=')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('=', _) => "=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => return Err(ParseError::new(start, "unexpected character '!'")),
+                };
+                i += op.len();
+                let op = if op == "==" { "=" } else { op };
+                tokens.push((start, Token::Operator(op)));
+            }
+            '"' => {
+                i += 1;
+                let mut word = String::new();
+                loop {
+                    match chars.get(i) {
+                        Some('"') => break,
+                        Some(c) => word.push(*c),
+                        None => return Err(ParseError::new(start, "unterminated quoted word")),
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push((start, Token::Word(word)));
+            }
+            _ if is_word_char(c) => {
+                let mut word = String::new();
+                while i < chars.len() && is_word_char(chars[i]) {
+                    word.push(chars[i]);
+                    i += 1;
+                }
+                let token = match word.to_ascii_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Word(word),
+                };
+                tokens.push((start, token));
+            }
+            _ => {
+                return Err(ParseError::new(
+                    start,
+                    format!("unexpected character '{c}'"),
+                ))
+            }
+        }
+    }
+    tokens.push((chars.len(), Token::End));
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '/' | '*')
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> (usize, Token) {
+        let (column, token) = &mut self.tokens[self.position];
+        let token = std::mem::replace(token, Token::End);
+        let column = *column;
+        if self.position + 1 < self.tokens.len() {
+            self.position += 1;
+        }
+        (column, token)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_and()?;
+        while *self.peek() == Token::Or {
+            self.next();
+            let rhs = self.parse_and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_unary()?;
+        while *self.peek() == Token::And {
+            self.next();
+            let rhs = self.parse_unary()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if *self.peek() == Token::Not {
+            self.next();
+            let expr = self.parse_unary()?;
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let (column, token) = self.next();
+        match token {
+            Token::LeftParen => {
+                let expr = self.parse_or()?;
+                let (column, token) = self.next();
+                if token != Token::RightParen {
+                    return Err(ParseError::new(
+                        column,
+                        format!("expected ')', found {}", token.describe()),
+                    ));
+                }
+                Ok(expr)
+            }
+            Token::Word(word) => {
+                if let Token::Operator(_) = self.peek() {
+                    self.parse_comparison(column, word)
+                } else {
+                    Ok(Expr::Tag(word.to_ascii_uppercase()))
+                }
+            }
+            token => Err(ParseError::new(
+                column,
+                format!("expected a tag or a comparison, found {}", token.describe()),
+            )),
+        }
+    }
+
+    fn parse_comparison(&mut self, column: usize, name: String) -> Result<Expr, ParseError> {
+        let field = match FIELDS.iter().find(|f| f.name.eq_ignore_ascii_case(&name)) {
+            Some(f) => f,
+            None => return Err(ParseError::new(column, format!("unknown field '{name}'"))),
+        };
+        let (op_column, op) = match self.next() {
+            (column, Token::Operator(op)) => (column, op),
+            _ => unreachable!(),
+        };
+        if field.kind == FieldKind::Text && !matches!(op, "=" | "!=") {
+            return Err(ParseError::new(
+                op_column,
+                format!(
+                    "operator '{op}' is not supported by text field '{}'",
+                    field.name
+                ),
+            ));
+        }
+        let (column, value) = match self.next() {
+            (column, Token::Word(value)) => (column, value),
+            (column, token) => {
+                return Err(ParseError::new(
+                    column,
+                    format!("expected a value, found {}", token.describe()),
+                ))
+            }
+        };
+        let value = match field.kind {
+            FieldKind::Text if field.name == "name" => Value::Text(value.to_ascii_uppercase()),
+            FieldKind::Text => Value::Text(value),
+            FieldKind::Integer => match value.parse::<i64>() {
+                Ok(n) if n >= 0 => Value::Integer(n),
+                _ => {
+                    return Err(ParseError::new(
+                        column,
+                        format!("'{value}' is not a valid integer"),
+                    ))
+                }
+            },
+            FieldKind::Real => match value.parse::<f64>() {
+                Ok(n) if n.is_finite() => Value::Real(n),
+                _ => {
+                    return Err(ParseError::new(
+                        column,
+                        format!("'{value}' is not a valid number"),
+                    ))
+                }
+            },
+            FieldKind::Duration => match value.parse::<crate::DurationArg>() {
+                Ok(d) => Value::Integer(d.0 as i64),
+                Err(_) => {
+                    return Err(ParseError::new(
+                        column,
+                        format!("'{value}' is not a valid duration"),
+                    ))
+                }
+            },
+        };
+        Ok(Expr::Compare(field, op, value))
+    }
+}
+
+pub fn parse(source: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let expr = parser.parse_or()?;
+    match parser.next() {
+        (_, Token::End) => Ok(expr),
+        (column, token) => Err(ParseError::new(
+            column,
+            format!("unexpected {}", token.describe()),
+        )),
+    }
+}
+
+impl Expr {
+    pub fn to_condition(&self) -> Condition {
+        match self {
+            Expr::Tag(tag) => crate::tag_condition(tag),
+            Expr::Compare(field, op, value) => {
+                let column = Column::new(field.name);
+                let value = value.clone();
+                match *op {
+                    "=" => column.eq(value),
+                    "!=" => column.ne(value),
+                    "<" => column.lt(value),
+                    "<=" => column.le(value),
+                    ">" => column.gt(value),
+                    ">=" => column.ge(value),
+                    _ => unreachable!(),
+                }
+            }
+            Expr::Not(expr) => !expr.to_condition(),
+            Expr::And(lhs, rhs) => lhs.to_condition().and(rhs.to_condition()),
+            Expr::Or(lhs, rhs) => lhs.to_condition().or(rhs.to_condition()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(expr: &Expr) -> String {
+        match expr {
+            Expr::Tag(tag) => tag.clone(),
+            Expr::Compare(field, op, value) => {
+                let value = match value {
+                    Value::Integer(n) => n.to_string(),
+                    Value::Real(n) => n.to_string(),
+                    Value::Text(s) => format!("{s:?}"),
+                    _ => unreachable!(),
+                };
+                format!("{}{op}{value}", field.name)
+            }
+            Expr::Not(expr) => format!("!{}", show(expr)),
+            Expr::And(lhs, rhs) => format!("({} & {})", show(lhs), show(rhs)),
+            Expr::Or(lhs, rhs) => format!("({} | {})", show(lhs), show(rhs)),
+        }
+    }
+
+    fn parsed(source: &str) -> String {
+        match parse(source) {
+            Ok(expr) => show(&expr),
+            Err(e) => panic!("{}", e.report(source)),
+        }
+    }
+
+    fn error(source: &str) -> String {
+        match parse(source) {
+            Ok(expr) => panic!("'{source}' is parsed as {}", show(&expr)),
+            Err(e) => e.report(source),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parsed("a or b and not c"), "(A | (B & !C))");
+        assert_eq!(parsed("a and b or c"), "((A & B) | C)");
+        assert_eq!(parsed("a and b and c"), "((A & B) & C)");
+        assert_eq!(parsed("not not a"), "!!A");
+        assert_eq!(parsed("A AND Not b Or c"), "((A & !B) | C)");
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(parsed("(a or b) and c"), "((A | B) & C)");
+        assert_eq!(parsed("not (a or b)"), "!(A | B)");
+        assert_eq!(parsed("((a))"), "A");
+        assert_eq!(
+            error("(a or b"),
+            "expected ')', found end of query\n  (a or b\n         ^"
+        );
+        assert_eq!(error("a)"), "unexpected ')'\n  a)\n   ^");
+        assert_eq!(
+            error("a and ()"),
+            "expected a tag or a comparison, found ')'\n  a and ()\n         ^"
+        );
+    }
+
+    #[test]
+    fn quoted_words() {
+        assert_eq!(
+            parsed(r#""outdoor beach" or "and""#),
+            "(OUTDOOR BEACH | AND)"
+        );
+        assert_eq!(parsed(r#"name="abc-1""#), r#"name="ABC-1""#);
+        assert_eq!(
+            parsed(r#"file_name="Abc 1.mp4""#),
+            r#"file_name="Abc 1.mp4""#
+        );
+        assert_eq!(
+            error(r#"a and "b c"#),
+            "unterminated quoted word\n  a and \"b c\n        ^"
+        );
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(parsed("rating==3"), "rating=3");
+        assert_eq!(parsed("rating = 3"), "rating=3");
+        assert_eq!(parsed("rating!=3"), "rating!=3");
+        assert_eq!(parsed("rating<=3 and rating>1"), "(rating<=3 & rating>1)");
+        assert_eq!(
+            error("rating ! 3"),
+            "unexpected character '!'\n  rating ! 3\n         ^"
+        );
+        assert_eq!(error("!a"), "unexpected character '!'\n  !a\n  ^");
+        assert_eq!(
+            error("name<abc"),
+            "operator '<' is not supported by text field 'name'\n  name<abc\n      ^"
+        );
+        assert_eq!(error("size>1"), "unknown field 'size'\n  size>1\n  ^");
+        assert_eq!(
+            error("rating>"),
+            "expected a value, found end of query\n  rating>\n         ^"
+        );
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(parsed("duration>1:30"), "duration>90");
+        assert_eq!(parsed("duration<1:02:03"), "duration<3723");
+        assert_eq!(parsed("duration=45"), "duration=45");
+        assert_eq!(parsed("video_frame_rate>=29.97"), "video_frame_rate>=29.97");
+        assert_eq!(parsed("video_frame_rate<30"), "video_frame_rate<30");
+        assert_eq!(
+            error("duration>1:x"),
+            "'1:x' is not a valid duration\n  duration>1:x\n           ^"
+        );
+        assert_eq!(
+            error("video_frame_rate>inf"),
+            "'inf' is not a valid number\n  video_frame_rate>inf\n                   ^"
+        );
+        assert_eq!(
+            error("rating>-1"),
+            "'-1' is not a valid integer\n  rating>-1\n         ^"
+        );
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        assert_eq!(
+            error("café @"),
+            "unexpected character '@'\n  café @\n       ^"
+        );
+        assert_eq!(
+            error("é and (été"),
+            "expected ')', found end of query\n  é and (été\n            ^"
+        );
+        assert_eq!(
+            error(r#""plage été" ""#),
+            "unterminated quoted word\n  \"plage été\" \"\n              ^"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use rusqlite::ToSql;
+use std::marker::PhantomData;
+
+/// A column of type `T`, generated by `#[derive(Select)]` as `Entry::cols().field`.
+pub struct Column<T> {
+    name: &'static str,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Column<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn asc(&self) -> Order {
+        Order::new(self.name)
+    }
+
+    pub fn desc(&self) -> Order {
+        Order::new(format!("{} DESC", self.name))
+    }
+}
+
+impl<T: ToSql + 'static> Column<T> {
+    fn compare(&self, op: &str, value: T) -> Condition {
+        Condition::new(format!("{}{op}?", self.name)).bind(value)
+    }
+
+    pub fn eq(&self, value: T) -> Condition {
+        self.compare("=", value)
+    }
+
+    pub fn ne(&self, value: T) -> Condition {
+        self.compare("<>", value)
+    }
+
+    pub fn lt(&self, value: T) -> Condition {
+        self.compare("<", value)
+    }
+
+    pub fn le(&self, value: T) -> Condition {
+        self.compare("<=", value)
+    }
+
+    pub fn gt(&self, value: T) -> Condition {
+        self.compare(">", value)
+    }
+
+    pub fn ge(&self, value: T) -> Condition {
+        self.compare(">=", value)
+    }
+
+    pub fn between(&self, min: T, max: T) -> Condition {
+        Condition::new(format!("{} BETWEEN ? AND ?", self.name))
+            .bind(min)
+            .bind(max)
+    }
+}
+
+impl Column<String> {
+    pub fn glob(&self, pattern: impl Into<String>) -> Condition {
+        Condition::new(format!("{} GLOB ?", self.name)).bind(pattern.into())
+    }
+}
+
+impl Column<bool> {
+    /// Unlike `eq(true)`, the condition can make use of partial indexes `WHERE column`.
+    pub fn is_true(&self) -> Condition {
+        Condition::new(self.name)
+    }
+}
+
+/// A boolean SQL expression with its parameters bound in order.
+pub struct Condition {
+    sql: String,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl Condition {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            params: vec![],
+        }
+    }
+
+    /// Bind the value to the next `?` of the expression.
+    pub fn bind<T: ToSql + 'static>(mut self, value: T) -> Self {
+        self.params.push(Box::new(value));
+        self
+    }
+
+    fn combine(mut self, op: &str, mut rhs: Condition) -> Self {
+        self.sql = format!("({} {op} {})", self.sql, rhs.sql);
+        self.params.append(&mut rhs.params);
+        self
+    }
+
+    pub fn and(self, rhs: Condition) -> Self {
+        self.combine("AND", rhs)
+    }
+
+    pub fn or(self, rhs: Condition) -> Self {
+        self.combine("OR", rhs)
+    }
+}
+
+impl std::ops::Not for Condition {
+    type Output = Condition;
+
+    fn not(mut self) -> Self {
+        self.sql = format!("NOT ({})", self.sql);
+        self
+    }
+}
+
+/// An expression to sort by, with its parameters bound in order.
+pub struct Order {
+    sql: String,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl Order {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            params: vec![],
+        }
+    }
+
+    /// Bind the value to the next `?` of the expression.
+    pub fn bind<T: ToSql + 'static>(mut self, value: T) -> Self {
+        self.params.push(Box::new(value));
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Query {
+    condition: Option<Condition>,
+    orders: Vec<Order>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the condition, which is joined with the previous ones by `AND`.
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.condition = match self.condition.take() {
+            Some(previous) => Some(previous.and(condition)),
+            None => Some(condition),
+        };
+        self
+    }
+
+    pub fn order_by(mut self, order: Order) -> Self {
+        self.orders.push(order);
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Return the clause after `SELECT ... FROM table`, and its parameters.
+    pub fn to_sql(&self) -> (String, Vec<&dyn ToSql>) {
+        let mut sql = String::new();
+        let mut params: Vec<&dyn ToSql> = vec![];
+        if let Some(condition) = &self.condition {
+            sql.push_str("WHERE ");
+            sql.push_str(&condition.sql);
+            params.extend(condition.params.iter().map(|p| p.as_ref()));
+        }
+        if !self.orders.is_empty() {
+            let orders: Vec<&str> = self.orders.iter().map(|o| o.sql.as_str()).collect();
+            push_clause(&mut sql, "ORDER BY ");
+            sql.push_str(&orders.join(", "));
+            for order in &self.orders {
+                params.extend(order.params.iter().map(|p| p.as_ref()));
+            }
+        }
+        // SQLite only accepts OFFSET after LIMIT, where a negative limit means no limit.
+        if self.limit.is_some() || self.offset.is_some() {
+            push_clause(&mut sql, "LIMIT ?");
+            params.push(match &self.limit {
+                Some(limit) => limit,
+                None => &-1,
+            });
+        }
+        if let Some(offset) = &self.offset {
+            sql.push_str(" OFFSET ?");
+            params.push(offset);
+        }
+        (sql, params)
+    }
+}
+
+fn push_clause(sql: &mut String, clause: &str) {
+    if !sql.is_empty() {
+        sql.push(' ');
+    }
+    sql.push_str(clause);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::types::{ToSqlOutput, Value};
+
+    const RATING: Column<u32> = Column::new("rating");
+    const NAME: Column<String> = Column::new("name");
+    const FAVORITE: Column<bool> = Column::new("favorite");
+
+    fn to_sql(query: &Query) -> (String, Vec<Value>) {
+        let (sql, params) = query.to_sql();
+        let values = params
+            .iter()
+            .map(|p| match p.to_sql().unwrap() {
+                ToSqlOutput::Owned(v) => v,
+                ToSqlOutput::Borrowed(v) => v.into(),
+                _ => unreachable!(),
+            })
+            .collect();
+        (sql, values)
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(to_sql(&Query::new()), (String::new(), vec![]));
+    }
+
+    #[test]
+    fn filter_order_offset() {
+        let query = Query::new()
+            .filter(RATING.ge(3))
+            .filter(
+                Condition::new("name IN (SELECT video FROM video_tag WHERE tag IN (?, ?))")
+                    .bind("A")
+                    .bind("B"),
+            )
+            .order_by(
+                Order::new(
+                    "(SELECT position FROM collection_video WHERE collection=? AND video=name)",
+                )
+                .bind("C"),
+            )
+            .order_by(NAME.asc())
+            .offset(20);
+        assert_eq!(
+            to_sql(&query),
+            (
+                String::from(
+                    "WHERE (rating>=? AND name IN (SELECT video FROM video_tag WHERE tag IN (?, ?))) \
+                    ORDER BY (SELECT position FROM collection_video WHERE collection=? AND video=name), name \
+                    LIMIT ? OFFSET ?"
+                ),
+                vec![
+                    Value::Integer(3),
+                    text("A"),
+                    text("B"),
+                    text("C"),
+                    Value::Integer(-1),
+                    Value::Integer(20)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn limit_and_offset() {
+        let query = Query::new().limit(10);
+        assert_eq!(
+            to_sql(&query),
+            (String::from("LIMIT ?"), vec![Value::Integer(10)])
+        );
+        let query = Query::new().offset(5).limit(10);
+        assert_eq!(
+            to_sql(&query),
+            (
+                String::from("LIMIT ? OFFSET ?"),
+                vec![Value::Integer(10), Value::Integer(5)]
+            )
+        );
+        let query = Query::new().order_by(RATING.desc()).limit(1);
+        assert_eq!(
+            to_sql(&query),
+            (
+                String::from("ORDER BY rating DESC LIMIT ?"),
+                vec![Value::Integer(1)]
+            )
+        );
+    }
+
+    #[test]
+    fn combined_conditions() {
+        let query = Query::new()
+            .filter(!(RATING.lt(2).or(NAME.glob("ABC*"))))
+            .filter(FAVORITE.is_true().and(RATING.between(1, 4)));
+        assert_eq!(
+            to_sql(&query),
+            (
+                String::from(
+                    "WHERE (NOT ((rating<? OR name GLOB ?)) AND (favorite AND rating BETWEEN ? AND ?))"
+                ),
+                vec![
+                    Value::Integer(2),
+                    text("ABC*"),
+                    Value::Integer(1),
+                    Value::Integer(4)
+                ]
+            )
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! The types used by the code generated by the derives of `macros`.
+
+pub mod dsl;
+mod schema;
+
+pub use schema::SchemaDiff;
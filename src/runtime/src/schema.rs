@@ -0,0 +1,58 @@
+/// The differences between a table and the fields of its entry, found by `sync_schema` of
+/// `#[derive(CreateTable)]`. Only the missing columns are added; the others need migrations.
+pub struct SchemaDiff {
+    pub table: &'static str,
+    pub created: bool,
+    pub added: Vec<String>,
+    /// The missing columns which cannot be added by `ALTER TABLE`, e.g. primary keys.
+    pub missing: Vec<String>,
+    /// The columns with their types in the database and the expected types.
+    pub changed: Vec<(String, String, String)>,
+    pub dropped: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn new(table: &'static str) -> Self {
+        Self {
+            table,
+            created: false,
+            added: vec![],
+            missing: vec![],
+            changed: vec![],
+            dropped: vec![],
+        }
+    }
+
+    pub fn describe(ty: &str, not_null: bool, primary: bool) -> String {
+        let mut s = ty.to_ascii_uppercase();
+        if not_null {
+            s.push_str(" NOT NULL");
+        }
+        if primary {
+            s.push_str(" PRIMARY KEY");
+        }
+        s
+    }
+
+    /// Describe the differences which are left to migrations.
+    pub fn problems(&self) -> Vec<String> {
+        let table = self.table;
+        let mut problems = vec![];
+        for column in &self.missing {
+            problems.push(format!(
+                "column '{column}' is missing from table '{table}', and cannot be added automatically"
+            ));
+        }
+        for (column, actual, expected) in &self.changed {
+            problems.push(format!(
+                "column '{column}' of table '{table}' is {actual}, but {expected} is expected"
+            ));
+        }
+        for column in &self.dropped {
+            problems.push(format!(
+                "column '{column}' of table '{table}' is no longer used"
+            ));
+        }
+        problems
+    }
+}